@@ -0,0 +1,50 @@
+use std::path::Path;
+
+/// Plays the audio files attached to cards.
+///
+/// Without the `audio` feature, playing a file only logs that playback is unavailable.
+#[derive(Default)]
+pub struct AudioPlayer {
+    #[cfg(feature = "audio")]
+    stream: Option<rodio::OutputStream>,
+    #[cfg(feature = "audio")]
+    sink: Option<rodio::Sink>,
+}
+
+impl AudioPlayer {
+    /// Plays the given file, stopping any audio that is still playing.
+    pub fn play(&mut self, path: &Path) {
+        if !path.exists() {
+            log::warn!("Audio file {} does not exist", path.display());
+            return;
+        }
+        #[cfg(feature = "audio")]
+        if let Err(err) = self.try_play(path) {
+            log::warn!("Failed to play {}: {}", path.display(), err);
+        }
+        #[cfg(not(feature = "audio"))]
+        log::info!(
+            "Not playing {}, ruvola was built without the audio feature",
+            path.display()
+        );
+    }
+
+    #[cfg(feature = "audio")]
+    fn try_play(&mut self, path: &Path) -> anyhow::Result<()> {
+        if let Some(sink) = self.sink.take() {
+            sink.stop();
+        }
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => {
+                let mut stream = rodio::OutputStreamBuilder::open_default_stream()?;
+                // Printing to stderr would garble the terminal UI
+                stream.log_on_drop(false);
+                self.stream.insert(stream)
+            }
+        };
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        self.sink = Some(rodio::play(stream.mixer(), file)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+pub(crate) mod archive;
+pub(crate) mod due;
+pub(crate) mod dump_config;
+pub(crate) mod export_sheet;
+pub(crate) mod extract_due;
+pub(crate) mod postpone;
+pub(crate) mod reset;
+pub(crate) mod test_answers;
+pub(crate) mod verify;
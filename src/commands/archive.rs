@@ -0,0 +1,164 @@
+use anyhow::{Result, anyhow};
+
+use crate::{
+    config::{AppConfig, SessionConfig},
+    model::voca_card::VocaCardDataset,
+};
+
+fn default_archive_path(file_path: &str) -> String {
+    format!("{}.archive", file_path)
+}
+
+fn resolve_archive_paths(
+    file_paths: &[String],
+    archive_file: Option<&str>,
+) -> Result<Vec<(String, String)>> {
+    match archive_file {
+        Some(_) if file_paths.len() > 1 => Err(anyhow!(
+            "An explicit archive file can only be used with a single deck file"
+        )),
+        Some(archive_file) => Ok(file_paths
+            .iter()
+            .map(|file_path| (file_path.clone(), archive_file.to_string()))
+            .collect()),
+        None => Ok(file_paths
+            .iter()
+            .map(|file_path| (file_path.clone(), default_archive_path(file_path)))
+            .collect()),
+    }
+}
+
+/// Loads the archive file for `deck`, or creates an empty one with the same languages. The
+/// archive is written like the deck, with the same overrides.
+fn load_archive(
+    archive_path: &str,
+    deck: &VocaCardDataset,
+    session: &SessionConfig,
+) -> Result<VocaCardDataset> {
+    if !std::fs::exists(archive_path)? {
+        let mut archive = VocaCardDataset::new(archive_path, &deck.lang_a, &deck.lang_b);
+        archive.encoding = session.encoding;
+        archive.apply_save_settings(session);
+        archive.overrides = deck.overrides.clone();
+        return Ok(archive);
+    }
+    let mut archive = VocaCardDataset::from_file_configured(archive_path, session)?;
+    archive.overrides = deck.overrides.clone();
+    if archive.lang_a != deck.lang_a || archive.lang_b != deck.lang_b {
+        return Err(anyhow!(
+            "Languages of archive '{}' do not match deck '{}'",
+            archive_path,
+            deck.file_path
+        ));
+    }
+    Ok(archive)
+}
+
+/// Moves all cards that reached `min_deck` in both directions into an archive file.
+pub fn archive(
+    file_paths: &[String],
+    archive_file: Option<&str>,
+    min_deck: u8,
+    config: &AppConfig,
+) -> Result<()> {
+    let session = &config.session;
+    for (file_path, archive_path) in resolve_archive_paths(file_paths, archive_file)? {
+        let mut deck = VocaCardDataset::from_file_configured(&file_path, session)?;
+        deck.overrides = config.deck_overrides.get(&file_path);
+        let forward_only = deck.overrides.forward_only;
+        let (learned, active) = deck
+            .cards
+            .into_iter()
+            .partition::<Vec<_>, _>(|card| card.is_learned(min_deck, forward_only));
+        deck.cards = active;
+        if learned.is_empty() {
+            println!("No cards to archive in '{}'", file_path);
+            continue;
+        }
+
+        let mut archive = load_archive(&archive_path, &deck, session)?;
+        let num_archived = learned.len();
+        archive.cards.extend(learned);
+        // Write the archive first so that no cards are lost if saving fails
+        archive.save()?;
+        deck.save()?;
+        println!(
+            "Archived {} cards from '{}' to '{}'",
+            num_archived, file_path, archive_path
+        );
+    }
+    Ok(())
+}
+
+/// Moves all cards from the archive file back into their deck file and removes the archive.
+pub fn unarchive(
+    file_paths: &[String],
+    archive_file: Option<&str>,
+    config: &AppConfig,
+) -> Result<()> {
+    let session = &config.session;
+    for (file_path, archive_path) in resolve_archive_paths(file_paths, archive_file)? {
+        if !std::fs::exists(&archive_path)? {
+            println!("No archive found for '{}'", file_path);
+            continue;
+        }
+        let mut deck = VocaCardDataset::from_file_configured(&file_path, session)?;
+        deck.overrides = config.deck_overrides.get(&file_path);
+        let archive = load_archive(&archive_path, &deck, session)?;
+        let num_restored = archive.cards.len();
+        deck.cards.extend(archive.cards);
+        deck.save()?;
+        std::fs::remove_file(&archive_path)?;
+        println!(
+            "Restored {} cards from '{}' to '{}'",
+            num_restored, archive_path, file_path
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LineEnding;
+
+    #[test]
+    fn archive_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ruvola-archive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let deck_path = dir.join("deck.tsv").to_string_lossy().to_string();
+        let content = "de\ten\n\
+            Hallo\tHello\t9\t2030-01-01 00:00:00\t9\t2030-01-01 00:00:00\n\
+            Bier\tBeer\t9\t2030-01-01 00:00:00\t2\t2030-01-01 00:00:00\n\
+            Tschüss\tBye\n";
+        std::fs::write(&deck_path, content).unwrap();
+        let file_paths = vec![deck_path.clone()];
+
+        let config = AppConfig {
+            session: SessionConfig {
+                line_ending: LineEnding::Crlf,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        archive(&file_paths, None, 8, &config).unwrap();
+        let deck = VocaCardDataset::from_file(&deck_path).unwrap();
+        let archived = VocaCardDataset::from_file(&default_archive_path(&deck_path)).unwrap();
+        assert_eq!(deck.cards.len(), 2);
+        assert_eq!(archived.cards.len(), 1);
+        assert_eq!(archived.cards[0].word_a.base, "Hallo");
+
+        assert!(
+            std::fs::read_to_string(default_archive_path(&deck_path))
+                .unwrap()
+                .ends_with("\r\n")
+        );
+
+        unarchive(&file_paths, None, &config).unwrap();
+        let deck = VocaCardDataset::from_file(&deck_path).unwrap();
+        assert_eq!(deck.cards.len(), 3);
+        assert!(!std::fs::exists(default_archive_path(&deck_path)).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::{Result, anyhow};
+use chrono::{Days, NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::{FilterMode, config::AppConfig, model::voca_card::VocaCardDataset};
+
+/// A scheduled card direction that is due by the queried date.
+struct DueCard<'a> {
+    file_path: &'a str,
+    query: &'a str,
+    answer: &'a str,
+    reverse: bool,
+    deck: u8,
+    due_date: NaiveDateTime,
+}
+
+/// All scheduled card directions that are due before `until`, the earliest first. Cards that were
+/// never answered are not scheduled and left out.
+fn due_cards(decks: &[VocaCardDataset], until: NaiveDateTime) -> Vec<DueCard<'_>> {
+    let mut due = Vec::new();
+    for deck in decks {
+        let directions: &[bool] = if deck.overrides.forward_only {
+            &[false]
+        } else {
+            &[false, true]
+        };
+        for card in &deck.cards {
+            let Some(metadata) = &card.metadata else {
+                continue;
+            };
+            for &reverse in directions {
+                if !card.is_due(reverse, FilterMode::Seen, until) {
+                    continue;
+                }
+                let (query, answer, deck_number, due_date) = if reverse {
+                    (
+                        &card.word_b,
+                        &card.word_a,
+                        metadata.deck_reverse,
+                        metadata.due_date_reverse,
+                    )
+                } else {
+                    (&card.word_a, &card.word_b, metadata.deck, metadata.due_date)
+                };
+                due.push(DueCard {
+                    file_path: &deck.file_path,
+                    query: &query.base,
+                    answer: &answer.base,
+                    reverse,
+                    deck: deck_number,
+                    due_date,
+                });
+            }
+        }
+    }
+    due.sort_by_key(|card| card.due_date);
+    due
+}
+
+/// Pads every column except the last one to the width of its widest cell.
+fn format_table(rows: &[Vec<String>]) -> String {
+    let mut widths = Vec::<usize>::new();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            if i == widths.len() {
+                widths.push(0);
+            }
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in rows {
+        let last = row.len().saturating_sub(1);
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            table.push_str(cell);
+            if i < last {
+                let padding = width - cell.chars().count() + 2;
+                table.extend(std::iter::repeat_n(' ', padding));
+            }
+        }
+        table.push('\n');
+    }
+    table
+}
+
+/// Lists the card directions that are due on or before `on`.
+pub fn due(file_paths: &[String], on: NaiveDate, config: &AppConfig) -> Result<()> {
+    let mut decks = file_paths
+        .iter()
+        .map(|file_path| VocaCardDataset::from_file_encoded(file_path, config.session.encoding))
+        .collect::<Result<Vec<_>, _>>()?;
+    for deck in &mut decks {
+        deck.overrides = config.deck_overrides.get(&deck.file_path);
+    }
+    let until = on
+        .checked_add_days(Days::new(1))
+        .ok_or_else(|| anyhow!("Invalid date {}", on))?
+        .and_time(NaiveTime::MIN);
+
+    let due = due_cards(&decks, until);
+    if due.is_empty() {
+        println!("No cards are due on {}", on);
+        return Ok(());
+    }
+    let mut rows = vec![
+        ["Due", "Query", "Answer", "Direction", "Deck", "File"]
+            .map(str::to_string)
+            .to_vec(),
+    ];
+    rows.extend(due.iter().map(|card| {
+        vec![
+            card.due_date.format("%Y-%m-%d %H:%M").to_string(),
+            card.query.to_string(),
+            card.answer.to_string(),
+            if card.reverse { "reverse" } else { "forward" }.to_string(),
+            card.deck.to_string(),
+            card.file_path.to_string(),
+        ]
+    }));
+    print!("{}", format_table(&rows));
+    println!("{} cards due on {}", due.len(), on);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::voca_card::{DATE_FORMAT, Vocab, VocabMetadata, VocabWord};
+
+    #[test]
+    fn cards_due_by_date() {
+        let date = |date| NaiveDateTime::parse_from_str(date, DATE_FORMAT).unwrap();
+        let mut deck = VocaCardDataset::new("deck.tsv", "de", "en");
+        for (word_a, word_b, metadata) in [
+            (
+                "Hallo",
+                "Hello",
+                Some((1, "2025-06-01 18:00:00", 2, "2025-06-02 08:00:00")),
+            ),
+            (
+                "Danke",
+                "Thanks",
+                Some((3, "2025-05-20 12:00:00", 3, "2025-07-01 12:00:00")),
+            ),
+            ("Bitte", "Please", None),
+        ] {
+            deck.cards.push(Vocab::new(
+                VocabWord::from_str(word_a),
+                VocabWord::from_str(word_b),
+                metadata.map(
+                    |(deck, due_date, deck_reverse, due_date_reverse)| VocabMetadata {
+                        deck,
+                        due_date: date(due_date),
+                        deck_reverse,
+                        due_date_reverse: date(due_date_reverse),
+                        ..Default::default()
+                    },
+                ),
+            ));
+        }
+        let until = date("2025-06-02 00:00:00");
+        let decks = [deck];
+        let due = due_cards(&decks, until)
+            .into_iter()
+            .map(|card| (card.query, card.reverse, card.deck))
+            .collect::<Vec<_>>();
+        assert_eq!(due, vec![("Danke", false, 3), ("Hallo", false, 1)]);
+    }
+
+    #[test]
+    fn table_columns() {
+        let rows = [vec!["a", "bb", "c"], vec!["dddd", "e", "f"]]
+            .map(|row| row.into_iter().map(str::to_string).collect::<Vec<_>>());
+        assert_eq!(format_table(&rows), "a     bb  c\ndddd  e   f\n");
+    }
+}
@@ -0,0 +1,10 @@
+use anyhow::Result;
+
+use crate::config::AppConfig;
+
+/// Prints the effective config, i.e. the defaults merged with all config files and environment
+/// overrides, as TOML.
+pub fn dump_config(config: &AppConfig) -> Result<()> {
+    print!("{}", toml::to_string(config)?);
+    Ok(())
+}
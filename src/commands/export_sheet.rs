@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow};
+
+use crate::{
+    config::FileEncoding,
+    model::voca_card::{VocaCardDataset, Vocab},
+};
+
+fn default_sheet_path(file_path: &str) -> String {
+    format!("{}.html", file_path)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn render_table(deck: &VocaCardDataset, cards: &[&Vocab], hide_answers: bool) -> String {
+    let rows = cards
+        .iter()
+        .map(|card| {
+            let answer = if hide_answers {
+                String::new()
+            } else {
+                escape_html(&card.word_b.base)
+            };
+            format!(
+                "<tr><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&card.word_a.base),
+                answer
+            )
+        })
+        .collect::<String>();
+    format!(
+        "<table>\n<tr><th>{}</th><th>{}</th></tr>\n{}</table>\n",
+        escape_html(&deck.lang_a),
+        escape_html(&deck.lang_b),
+        rows
+    )
+}
+
+/// Renders all cards of `deck` as a printable HTML page.
+fn render_sheet(deck: &VocaCardDataset, group_by_deck: bool, hide_answers: bool) -> String {
+    let body = if group_by_deck {
+        // Unseen cards have no deck and are listed first
+        let mut groups: BTreeMap<Option<u8>, Vec<&Vocab>> = BTreeMap::new();
+        for card in &deck.cards {
+            groups.entry(card.get_deck(false)).or_default().push(card);
+        }
+        groups
+            .iter()
+            .map(|(deck_number, cards)| {
+                let title = match deck_number {
+                    Some(deck_number) => format!("Deck {}", deck_number),
+                    None => "Unseen".to_string(),
+                };
+                format!(
+                    "<h2>{}</h2>\n{}",
+                    title,
+                    render_table(deck, cards, hide_answers)
+                )
+            })
+            .collect::<String>()
+    } else {
+        render_table(deck, &deck.cards.iter().collect::<Vec<_>>(), hide_answers)
+    };
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; width: 100%; page-break-inside: auto; }}
+tr {{ page-break-inside: avoid; }}
+th, td {{ border: 1px solid #999; padding: 0.4em; width: 50%; text-align: left; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{body}</body>
+</html>
+",
+        title = escape_html(&deck.file_path),
+        body = body
+    )
+}
+
+/// Writes a printable HTML study sheet for each vocab file.
+pub fn export_sheet(
+    file_paths: &[String],
+    output: Option<&str>,
+    group_by_deck: bool,
+    hide_answers: bool,
+    encoding: FileEncoding,
+) -> Result<()> {
+    if output.is_some() && file_paths.len() > 1 {
+        return Err(anyhow!(
+            "An explicit output file can only be used with a single deck file"
+        ));
+    }
+    for file_path in file_paths {
+        let deck = VocaCardDataset::from_file_encoded(file_path, encoding)?;
+        let sheet_path = output
+            .map(str::to_string)
+            .unwrap_or_else(|| default_sheet_path(file_path));
+        std::fs::write(
+            &sheet_path,
+            render_sheet(&deck, group_by_deck, hide_answers),
+        )?;
+        println!(
+            "Exported {} cards from '{}' to '{}'",
+            deck.cards.len(),
+            file_path,
+            sheet_path
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::voca_card::VocabWord;
+
+    #[test]
+    fn render_grouped_sheet() {
+        let mut deck = VocaCardDataset::new("deck.tsv", "de", "en");
+        for (word_a, word_b) in [("Fisch & Chips", "Fish <and> chips"), ("Bier", "Beer")] {
+            deck.cards.push(Vocab::new(
+                VocabWord::from_str(word_a),
+                VocabWord::from_str(word_b),
+                None,
+            ));
+        }
+        let sheet = render_sheet(&deck, true, false);
+        assert!(sheet.contains("<h2>Unseen</h2>"));
+        assert!(sheet.contains("<td>Fisch &amp; Chips</td><td>Fish &lt;and&gt; chips</td>"));
+
+        let sheet = render_sheet(&deck, false, true);
+        assert!(!sheet.contains("<h2>"));
+        assert!(sheet.contains("<td>Bier</td><td></td>"));
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{Result, anyhow, bail};
+use chrono::NaiveDateTime;
+
+use crate::{
+    FilterMode,
+    config::AppConfig,
+    model::voca_card::{VocaCardDataset, Vocab},
+};
+
+/// Copies of the cards of `deck` that are due in a tested direction at `now`, with their metadata.
+fn due_cards(deck: &VocaCardDataset, now: NaiveDateTime) -> impl Iterator<Item = Vocab> {
+    deck.cards
+        .iter()
+        .filter(move |card| {
+            card.is_due(false, FilterMode::Normal, now)
+                || (!deck.overrides.forward_only && card.is_due(true, FilterMode::Normal, now))
+        })
+        .cloned()
+}
+
+/// Writes the cards of all vocab files that are currently due into the new vocab file `output`.
+pub fn extract_due(file_paths: &[String], output: &str, config: &AppConfig) -> Result<()> {
+    if std::fs::exists(output)? {
+        bail!("'{}' already exists", output);
+    }
+    let mut decks = file_paths
+        .iter()
+        .map(|file_path| VocaCardDataset::from_file_configured(file_path, &config.session))
+        .collect::<Result<Vec<_>, _>>()?;
+    for deck in &mut decks {
+        deck.overrides = config.deck_overrides.get(&deck.file_path);
+    }
+    let first = decks
+        .first()
+        .ok_or_else(|| anyhow!("No vocab files given"))?;
+    if let Some(deck) = decks
+        .iter()
+        .find(|deck| deck.lang_a != first.lang_a || deck.lang_b != first.lang_b)
+    {
+        bail!(
+            "Languages of '{}' do not match '{}'",
+            deck.file_path,
+            first.file_path
+        );
+    }
+
+    let now = chrono::Local::now().naive_utc();
+    let mut extracted = VocaCardDataset::new(output, &first.lang_a, &first.lang_b);
+    extracted.encoding = config.session.encoding;
+    extracted.apply_save_settings(&config.session);
+    for deck in &decks {
+        extracted.cards.extend(due_cards(deck, now));
+    }
+    if extracted.cards.is_empty() {
+        println!("No cards are due");
+        return Ok(());
+    }
+    extracted.save()?;
+    println!(
+        "Extracted {} due cards to '{}'",
+        extracted.cards.len(),
+        output
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::voca_card::{DATE_FORMAT, VocabMetadata, VocabWord};
+    use chrono::Duration;
+
+    #[test]
+    fn extract_due_cards() {
+        let now = NaiveDateTime::parse_from_str("2025-01-31 00:00:00", DATE_FORMAT).unwrap();
+        let mut deck = VocaCardDataset::new("deck.tsv", "de", "en");
+        for (word, metadata) in [
+            (
+                "due",
+                Some(VocabMetadata {
+                    deck: 3,
+                    due_date: now - Duration::days(1),
+                    due_date_reverse: now + Duration::days(5),
+                    ..Default::default()
+                }),
+            ),
+            (
+                "due in reverse",
+                Some(VocabMetadata {
+                    due_date: now + Duration::days(5),
+                    due_date_reverse: now - Duration::days(1),
+                    ..Default::default()
+                }),
+            ),
+            (
+                "not due",
+                Some(VocabMetadata {
+                    due_date: now + Duration::days(5),
+                    due_date_reverse: now + Duration::days(5),
+                    ..Default::default()
+                }),
+            ),
+            ("new", None),
+        ] {
+            deck.cards.push(Vocab::new(
+                VocabWord::from_str(word),
+                VocabWord::from_str(word),
+                metadata,
+            ));
+        }
+        let words = |deck: &VocaCardDataset| {
+            due_cards(deck, now)
+                .map(|card| card.word_a.base)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words(&deck), ["due", "due in reverse", "new"]);
+        assert_eq!(
+            due_cards(&deck, now).next().unwrap().metadata.unwrap().deck,
+            3
+        );
+
+        deck.overrides.forward_only = true;
+        assert_eq!(words(&deck), ["due", "new"]);
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDateTime};
+
+use crate::{config::SessionConfig, model::voca_card::VocaCardDataset};
+
+/// Spreads the due dates of all overdue card directions evenly over `spread`, starting at
+/// `now`. The most overdue directions stay due first. Returns the number of postponed directions.
+fn spread_overdue(decks: &mut [VocaCardDataset], now: NaiveDateTime, spread: Duration) -> usize {
+    // (deck, card, reverse, due date) of every scheduled direction that is overdue
+    let mut overdue = Vec::new();
+    for (i, deck) in decks.iter().enumerate() {
+        for (j, card) in deck.cards.iter().enumerate() {
+            let Some(metadata) = &card.metadata else {
+                continue;
+            };
+            for (reverse, due_date) in [
+                (false, metadata.due_date),
+                (true, metadata.due_date_reverse),
+            ] {
+                // Directions that only finished the memorization round have never been scheduled
+                if due_date > DateTime::UNIX_EPOCH.naive_utc() && due_date < now {
+                    overdue.push((i, j, reverse, due_date));
+                }
+            }
+        }
+    }
+    overdue.sort_by_key(|(_, _, _, due_date)| *due_date);
+
+    let count = overdue.len();
+    for (n, (i, j, reverse, _)) in overdue.into_iter().enumerate() {
+        let card = &mut decks[i].cards[j];
+        let deck = card.get_deck(reverse).unwrap_or(0);
+        let offset = spread * n as i32 / count as i32;
+        card.update_metadata(deck, now + offset, reverse);
+    }
+    count
+}
+
+/// Postpones all overdue cards so that they become due spread out over `spread`.
+pub fn postpone(file_paths: &[String], spread: Duration, session: &SessionConfig) -> Result<()> {
+    let mut decks = file_paths
+        .iter()
+        .map(|file_path| VocaCardDataset::from_file_configured(file_path, session))
+        .collect::<Result<Vec<_>, _>>()?;
+    let count = spread_overdue(&mut decks, chrono::Local::now().naive_utc(), spread);
+    if count == 0 {
+        println!("No overdue cards");
+        return Ok(());
+    }
+    for deck in &decks {
+        deck.save()?;
+    }
+    println!("Postponed {} overdue cards", count);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::voca_card::{DATE_FORMAT, Vocab, VocabMetadata, VocabWord};
+
+    #[test]
+    fn spread_overdue_cards() {
+        let now = NaiveDateTime::parse_from_str("2025-01-31 00:00:00", DATE_FORMAT).unwrap();
+        let mut deck = VocaCardDataset::new("deck.tsv", "de", "en");
+        for days_overdue in [10, 20] {
+            deck.cards.push(Vocab::new(
+                VocabWord::from_str("Hallo"),
+                VocabWord::from_str("Hello"),
+                Some(VocabMetadata {
+                    deck: 3,
+                    due_date: now - Duration::days(days_overdue),
+                    // Never scheduled in reverse, so it is not postponed
+                    ..Default::default()
+                }),
+            ));
+        }
+        let mut decks = [deck];
+        assert_eq!(spread_overdue(&mut decks, now, Duration::days(30)), 2);
+
+        let metadata = |card: usize| decks[0].cards[card].metadata.clone().unwrap();
+        // The most overdue card stays due today
+        assert_eq!(metadata(1).due_date, now);
+        assert_eq!(metadata(0).due_date, now + Duration::days(15));
+        assert_eq!(metadata(0).deck, 3);
+        assert_eq!(
+            metadata(0).due_date_reverse,
+            DateTime::UNIX_EPOCH.naive_utc()
+        );
+    }
+}
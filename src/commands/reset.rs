@@ -0,0 +1,137 @@
+use std::io::Write;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime};
+
+use crate::{config::AppConfig, model::voca_card::VocaCardDataset};
+
+/// Makes all cards unseen again. With `keep_decks`, scheduled directions keep their deck and
+/// become due at `now` instead. Returns the number of reset cards.
+fn reset_cards(deck: &mut VocaCardDataset, keep_decks: bool, now: NaiveDateTime) -> usize {
+    let mut count = 0;
+    for card in &mut deck.cards {
+        let Some(metadata) = &mut card.metadata else {
+            continue;
+        };
+        count += 1;
+        if !keep_decks {
+            card.metadata = None;
+            card.lapses = 0;
+            continue;
+        }
+        // Directions that only finished the memorization round have never been scheduled
+        for due_date in [&mut metadata.due_date, &mut metadata.due_date_reverse] {
+            if *due_date > DateTime::UNIX_EPOCH.naive_utc() {
+                *due_date = now;
+            }
+        }
+    }
+    count
+}
+
+/// Clears the learning progress of all cards after asking for confirmation, unless `yes` is set.
+pub fn reset(file_paths: &[String], keep_decks: bool, yes: bool, config: &AppConfig) -> Result<()> {
+    let mut decks = file_paths
+        .iter()
+        .map(|file_path| VocaCardDataset::from_file_configured(file_path, &config.session))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !yes {
+        let action = if keep_decks {
+            "make all cards due now in"
+        } else {
+            "clear the progress of all cards in"
+        };
+        print!(
+            "This will {} {}. Continue? [y/N] ",
+            action,
+            file_paths.join(", ")
+        );
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            println!("Reset aborted");
+            return Ok(());
+        }
+    }
+    let now = chrono::Local::now().naive_utc();
+    for deck in &mut decks {
+        let count = reset_cards(deck, keep_decks, now);
+        deck.save()?;
+        println!("Reset {} cards in {}", count, deck.file_path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{FileEncoding, LineEnding},
+        model::voca_card::{DATE_FORMAT, Vocab, VocabMetadata, VocabWord},
+    };
+    use chrono::Duration;
+
+    fn deck(now: NaiveDateTime) -> VocaCardDataset {
+        let mut deck = VocaCardDataset::new("deck.tsv", "de", "en");
+        for metadata in [
+            Some(VocabMetadata {
+                deck: 4,
+                due_date: now + Duration::days(20),
+                // Only memorized in reverse
+                ..Default::default()
+            }),
+            None,
+        ] {
+            deck.cards.push(Vocab {
+                lapses: 2,
+                ..Vocab::new(
+                    VocabWord::from_str("Hallo"),
+                    VocabWord::from_str("Hello"),
+                    metadata,
+                )
+            });
+        }
+        deck
+    }
+
+    #[test]
+    fn reset_progress() {
+        let now = NaiveDateTime::parse_from_str("2025-01-31 00:00:00", DATE_FORMAT).unwrap();
+        let mut cleared = deck(now);
+        assert_eq!(reset_cards(&mut cleared, false, now), 1);
+        assert!(cleared.cards[0].metadata.is_none());
+        assert_eq!(cleared.cards[0].lapses, 0);
+
+        let mut kept = deck(now);
+        assert_eq!(reset_cards(&mut kept, true, now), 1);
+        let metadata = kept.cards[0].metadata.clone().unwrap();
+        assert_eq!(metadata.deck, 4);
+        assert_eq!(metadata.due_date, now);
+        assert_eq!(metadata.due_date_reverse, DateTime::UNIX_EPOCH.naive_utc());
+        assert_eq!(kept.cards[0].lapses, 2);
+        assert!(kept.cards[1].metadata.is_none());
+    }
+
+    #[test]
+    fn reset_keeps_file_format() {
+        let dir = std::env::temp_dir().join(format!("ruvola-reset-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("deck.tsv").to_string_lossy().to_string();
+        std::fs::write(
+            &file_path,
+            b"de\ten\r\nTsch\xfcss\tBye\t1\t2030-01-01 00:00:00\r\n",
+        )
+        .unwrap();
+        let mut config = AppConfig::default();
+        config.session.encoding = FileEncoding(encoding_rs::WINDOWS_1252);
+        config.session.line_ending = LineEnding::Crlf;
+
+        reset(std::slice::from_ref(&file_path), false, true, &config).unwrap();
+        assert_eq!(
+            std::fs::read(&file_path).unwrap(),
+            b"de\ten\r\nTsch\xfcss\tBye\r\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{Result, anyhow, bail};
+
+use crate::{
+    config::{AppConfig, ValidationConfig},
+    model::{voca_card::VocaCardDataset, voca_session::VocabTask},
+};
+
+struct GradedAnswer {
+    accepted: bool,
+    /// Closest accepted variant of all cards with the query and its edit distance
+    nearest: Option<(String, usize)>,
+}
+
+/// Grades `answer` against every card direction whose query is `query`.
+fn grade(
+    decks: &[VocaCardDataset],
+    query: &str,
+    answer: &str,
+    validation: &ValidationConfig,
+) -> GradedAnswer {
+    let tasks = decks.iter().flat_map(|deck| {
+        deck.cards.iter().flat_map(move |card| {
+            let directions: &[bool] = if deck.overrides.forward_only {
+                &[false]
+            } else {
+                &[false, true]
+            };
+            directions
+                .iter()
+                .map(move |&reverse| VocabTask::new(deck, card, reverse))
+        })
+    });
+    let mut graded = GradedAnswer {
+        accepted: false,
+        nearest: None,
+    };
+    for task in tasks.filter(|task| task.query == query) {
+        graded.accepted |= task.is_correct(answer, validation);
+        if let Some((variant, distance)) = task.nearest_variant(answer)
+            && graded
+                .nearest
+                .as_ref()
+                .is_none_or(|(_, nearest)| distance < *nearest)
+        {
+            graded.nearest = Some((variant.to_string(), distance));
+        }
+    }
+    graded
+}
+
+/// Reports for every `query<TAB>answer` line of `input` whether the answer would be accepted.
+/// Fails if any answer is rejected.
+pub fn test_answers(file_paths: &[String], input: &str, config: &AppConfig) -> Result<()> {
+    let mut decks = file_paths
+        .iter()
+        .map(|file_path| VocaCardDataset::from_file_encoded(file_path, config.session.encoding))
+        .collect::<Result<Vec<_>, _>>()?;
+    for deck in &mut decks {
+        deck.overrides = config.deck_overrides.get(&deck.file_path);
+        deck.expand_synonyms(&config.synonyms);
+    }
+
+    let content = std::fs::read_to_string(input)?;
+    let mut total = 0;
+    let mut rejected = 0;
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (query, answer) = line.split_once('\t').ok_or_else(|| {
+            anyhow!(
+                "Expected a query and an answer in '{}', line {}",
+                input,
+                i + 1
+            )
+        })?;
+        let graded = grade(&decks, query, answer, &config.validation);
+        let nearest = match &graded.nearest {
+            Some((variant, distance)) => format!("{} ({})", variant, distance),
+            None => "no card with this query".to_string(),
+        };
+        println!(
+            "{}\t{}\t{}\t{}",
+            if graded.accepted { "PASS" } else { "FAIL" },
+            query,
+            answer,
+            nearest
+        );
+        total += 1;
+        if !graded.accepted {
+            rejected += 1;
+        }
+    }
+    println!("{} of {} answers accepted", total - rejected, total);
+    if rejected > 0 {
+        bail!("{} answers were rejected", rejected);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::voca_card::{Vocab, VocabWord};
+
+    #[test]
+    fn grade_answers() {
+        let mut deck = VocaCardDataset::new("deck.tsv", "en", "es");
+        deck.cards.push(Vocab::new(
+            VocabWord::from_str("hello"),
+            VocabWord::from_str("hola, buenos días"),
+            None,
+        ));
+        let decks = [deck];
+        let validation = ValidationConfig::default();
+
+        let graded = grade(&decks, "hello", "buenos dias", &validation);
+        assert!(graded.accepted);
+        assert_eq!(graded.nearest, Some(("buenos días".to_string(), 1)));
+
+        let graded = grade(&decks, "hello", "ola", &validation);
+        assert!(!graded.accepted);
+        assert_eq!(graded.nearest, Some(("hola".to_string(), 1)));
+
+        let graded = grade(&decks, "hola, buenos días", "hello", &validation);
+        assert!(graded.accepted);
+
+        let graded = grade(&decks, "bye", "adiós", &validation);
+        assert!(!graded.accepted);
+        assert_eq!(graded.nearest, None);
+    }
+}
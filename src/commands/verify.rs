@@ -0,0 +1,81 @@
+use anyhow::{Result, bail};
+
+use crate::{config::AppConfig, model::voca_card::VocaCardDataset};
+
+/// Lines that differ between the `original` text of a file and the text it is `saved` as. Line
+/// endings and other whitespace are kept, so that every change is visible.
+fn diff_lines(original: &str, saved: &str) -> Vec<String> {
+    let original = original.split_inclusive('\n').collect::<Vec<_>>();
+    let saved = saved.split_inclusive('\n').collect::<Vec<_>>();
+    let mut differences = Vec::new();
+    for i in 0..original.len().max(saved.len()) {
+        match (original.get(i), saved.get(i)) {
+            (Some(original), Some(saved)) if original == saved => {}
+            (Some(original), Some(saved)) => {
+                differences.push(format!("line {}: {:?} -> {:?}", i + 1, original, saved))
+            }
+            (Some(original), None) => {
+                differences.push(format!("line {}: {:?} is removed", i + 1, original))
+            }
+            (None, Some(saved)) => {
+                differences.push(format!("line {}: {:?} is added", i + 1, saved))
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    differences
+}
+
+/// Loads every vocab file with the settings of a session and reports how saving it would change
+/// the file. Fails if any file would change.
+pub fn verify(file_paths: &[String], config: &AppConfig) -> Result<()> {
+    let mut changed = 0;
+    for file_path in file_paths {
+        let mut deck = VocaCardDataset::from_file_configured(file_path, &config.session)?;
+        deck.overrides = config.deck_overrides.get(&deck.file_path);
+
+        let original = VocaCardDataset::read_text(file_path, config.session.encoding)?;
+        let differences = diff_lines(&original, &deck.to_text());
+        if differences.is_empty() {
+            println!("{}: unchanged", file_path);
+            continue;
+        }
+        changed += 1;
+        println!("{}: {} lines would change", file_path, differences.len());
+        for difference in differences {
+            println!("  {}", difference);
+        }
+    }
+    if changed > 0 {
+        bail!(
+            "{} of {} files would change when saved",
+            changed,
+            file_paths.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_lines() {
+        assert!(diff_lines("de\ten\nHallo\tHello\n", "de\ten\nHallo\tHello\n").is_empty());
+        assert_eq!(
+            diff_lines(
+                "de\ten\r\nHallo \tHello\r\n\n",
+                "de\ten\r\nHallo\tHello\r\n"
+            ),
+            vec![
+                r#"line 2: "Hallo \tHello\r\n" -> "Hallo\tHello\r\n""#,
+                r#"line 3: "\n" is removed"#,
+            ]
+        );
+        assert_eq!(
+            diff_lines("de\ten", "de\ten\n"),
+            vec![r#"line 1: "de\ten" -> "de\ten\n""#]
+        );
+    }
+}
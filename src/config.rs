@@ -1,63 +1,201 @@
 use anyhow::Result;
 use chrono::Duration;
+use ratatui::{layout::Rect, style::Color};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
-#[derive(Deserialize, Debug, Default, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
 #[serde(default, deny_unknown_fields)]
 pub struct AppConfig {
     pub memorization: MemorizationConfig,
     pub validation: ValidationConfig,
     pub deck_config: DeckConfig,
     pub special_letters: SpecialLetters,
+    pub substitutions: Substitutions,
+    pub synonyms: Synonyms,
     pub keybindings: KeybindsConfig,
+    pub languages: LanguagesConfig,
+    pub session: SessionConfig,
+    pub theme: ThemeConfig,
+    pub deck_overrides: DeckOverrides,
+    pub goals: GoalsConfig,
+    pub dictionary: DictionaryConfig,
+    pub speech: SpeechConfig,
 }
 
 impl AppConfig {
     pub fn load_from_config_file(local_path: Option<&str>) -> Result<Self> {
-        const LOCAL_CONFIG_FILE: &str = "./ruvola.toml";
-        let local_config_path = local_path.unwrap_or(LOCAL_CONFIG_FILE);
-
-        let config_path = get_system_config_dir()?;
-        let config_file = format!("{}/ruvola/config.toml", config_path);
-        if std::fs::exists(&config_file)? {
-            let base_config = toml::de::from_str(&std::fs::read_to_string(&config_file)?)?;
-            if std::fs::exists(local_config_path)? {
-                let override_config =
-                    toml::de::from_str(&std::fs::read_to_string(local_config_path)?)?;
-                let merged_config = deep_override_config(base_config, override_config);
-                Ok(merged_config.try_into()?)
-            } else {
-                Ok(base_config.try_into()?)
+        const LOCAL_CONFIG_FILES: [&str; 2] = ["./ruvola.toml", "./ruvola.json"];
+        let local_config_path = match local_path {
+            Some(local_path) => Some(local_path.to_string()),
+            None => first_existing(LOCAL_CONFIG_FILES.map(str::to_string))?,
+        };
+
+        let mut config = toml::Value::Table(toml::Table::new());
+        let config_path = match get_system_config_dir() {
+            Ok(config_path) => Some(config_path),
+            // An explicitly requested config file is an error if it cannot be loaded
+            Err(err) if local_path.is_some() => {
+                return Err(anyhow::anyhow!(
+                    "Cannot locate the config directory: {}",
+                    err
+                ));
             }
-        } else {
-            Ok(Self::default())
+            // Printed before the terminal UI starts, so that it is not missed
+            Err(err) => {
+                eprintln!(
+                    "Warning: Cannot locate the config directory, using the default config: {}",
+                    err
+                );
+                None
+            }
+        };
+        let config_file = match config_path {
+            Some(config_path) => first_existing(
+                ["toml", "json"]
+                    .map(|extension| format!("{}/ruvola/config.{}", config_path, extension)),
+            )?,
+            None => None,
+        };
+        if let Some(config_file) = config_file {
+            config = read_config_value(&config_file)?;
+            if let Some(local_config_path) = local_config_path
+                && std::fs::exists(&local_config_path)?
+            {
+                let override_config = read_config_value(&local_config_path)?;
+                config = deep_override_config(config, override_config);
+            }
+        }
+        let config = deep_override_config(config, env_overrides(std::env::vars()));
+        Ok(config.try_into()?)
+    }
+}
+
+/// The first of `paths` that exists.
+fn first_existing(paths: impl IntoIterator<Item = String>) -> Result<Option<String>> {
+    for path in paths {
+        if std::fs::exists(&path)? {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// Reads a config file as TOML, or as JSON if its extension is `.json`.
+fn read_config_value(path: &str) -> Result<toml::Value> {
+    let content = std::fs::read_to_string(path)?;
+    let is_json = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    Ok(if is_json {
+        serde_json::from_str(&content)?
+    } else {
+        toml::de::from_str(&content)?
+    })
+}
+
+/// Builds a config override from `RUVOLA_<SECTION>_<KEY>` environment variables.
+///
+/// Values are parsed as TOML values and fall back to plain strings. Variables that do not
+/// start with a known section are ignored.
+fn env_overrides(vars: impl Iterator<Item = (String, String)>) -> toml::Value {
+    const SECTIONS: [&str; 12] = [
+        "memorization",
+        "validation",
+        "deck_config",
+        "special_letters",
+        "substitutions",
+        "keybindings",
+        "languages",
+        "session",
+        "theme",
+        "goals",
+        "dictionary",
+        "speech",
+    ];
+    let mut overrides = toml::Table::new();
+    for (name, value) in vars {
+        let Some(name) = name.strip_prefix("RUVOLA_") else {
+            continue;
+        };
+        let name = name.to_lowercase();
+        let Some((section, key)) = SECTIONS.iter().find_map(|section| {
+            name.strip_prefix(section)
+                .and_then(|rest| rest.strip_prefix('_'))
+                .map(|key| (*section, key))
+        }) else {
+            continue;
+        };
+        let value = toml::de::from_str::<toml::Table>(&format!("value = {}", value))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or(toml::Value::String(value));
+        if let toml::Value::Table(section) = overrides
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        {
+            section.insert(key.to_string(), value);
         }
     }
+    toml::Value::Table(overrides)
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 #[serde(default, deny_unknown_fields)]
 pub struct KeybindsConfig {
     pub skip: char,
+    pub continue_review: char,
     pub accept_anyway: char,
     pub reject_anyway: char,
+    pub show_variants: char,
+    pub lookup: char,
+    pub play_audio: char,
+    pub decrease_deck: char,
+    pub increase_deck: char,
+    pub repeat_special_letter: char,
     pub force_quit: char,
     pub save_and_quit: char,
     pub edit_mode: char,
     pub help: char,
+    pub stats: char,
+    pub history: char,
+    pub mark_known: char,
+    pub split_file: char,
+    pub focus: char,
+    pub work_ahead: char,
+    pub peek: char,
+    pub study_mode: char,
+    pub toggle_readings: char,
+    pub add_card: char,
 }
 
 impl Default for KeybindsConfig {
     fn default() -> Self {
         Self {
             skip: 's',
+            continue_review: 'c',
             accept_anyway: 'a',
             reject_anyway: 'r',
+            show_variants: 'v',
+            lookup: 'l',
+            play_audio: 'p',
+            decrease_deck: '-',
+            increase_deck: '+',
+            repeat_special_letter: '.',
             force_quit: 'Q',
             save_and_quit: 'w',
             edit_mode: 'i',
             help: 'h',
+            stats: 'S',
+            history: 'H',
+            mark_known: 'k',
+            split_file: 'X',
+            focus: 'F',
+            work_ahead: 'n',
+            peek: '?',
+            study_mode: 'm',
+            toggle_readings: 'f',
+            add_card: 'A',
         }
     }
 }
@@ -67,6 +205,10 @@ impl Default for KeybindsConfig {
 pub struct MemorizationConfig {
     pub do_memorization_round: bool,
     pub memorization_reversed: bool,
+    /// Minimum number of new cards needed for the memorization round to be shown
+    pub memorization_min_new_cards: usize,
+    /// Show the review screen after correct answers to memorization cards
+    pub memorization_review: bool,
 }
 
 impl Default for MemorizationConfig {
@@ -74,22 +216,404 @@ impl Default for MemorizationConfig {
         Self {
             do_memorization_round: true,
             memorization_reversed: false,
+            memorization_min_new_cards: 1,
+            memorization_review: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Number of skips of a direction within a session after which it is buried until midnight
+    pub max_skips_per_card: Option<usize>,
+    /// Start in edit mode whenever a new card is queried
+    pub auto_edit_on_query: bool,
+    /// How cards that are answered wrong count towards the progress
+    pub progress_mode: ProgressMode,
+    /// Number of due cards studied at once. Wrong answers are repeated within the batch, which
+    /// is refilled from the remaining due cards as cards are completed.
+    pub study_batch_size: Option<usize>,
+    /// Allow line breaks in answers with Shift+Enter
+    pub multiline_input: bool,
+    /// Append every review to a `<file>.history` file next to the vocab file
+    pub record_history: bool,
+    /// Pad the columns of saved vocab files with spaces so that they line up
+    pub save_aligned: bool,
+    /// Write all metadata columns for every card, so that all lines have the same width
+    pub uniform_columns: bool,
+    /// Line ending of saved vocab files
+    pub line_ending: LineEnding,
+    /// Character encoding of vocab files, used for loading and saving
+    pub encoding: FileEncoding,
+    /// What Ctrl+C does during a session
+    pub ctrl_c: CtrlCBehavior,
+    /// Order of the two directions of a card in the queue
+    pub pairing: Pairing,
+    /// Seconds without a key press after which the session timer pauses. 0 never pauses
+    pub idle_timeout_seconds: u64,
+    /// Number of due cards in the highest decks that are moved to the start of the session
+    pub warmup_count: usize,
+    /// Show the bracketed parts of the answer next to the query, e.g. `(der)` of `Tisch (der)`
+    pub show_answer_hints: bool,
+    /// Show the readings of the queried words above them
+    pub show_readings: bool,
+    /// How a card is graded after its answer was peeked at
+    pub peek_grading: PeekGrading,
+    /// Milliseconds after a card is shown before an answer can be submitted. 0 disables it
+    pub think_time_ms: u64,
+    /// How cards with the same word on both sides are tested
+    pub identical_words: IdenticalWords,
+    /// With `--work-ahead`, cards due soon are added once fewer tasks than this are left.
+    /// 0 only offers them once no card is left
+    pub work_ahead_low_watermark: usize,
+    /// Number of cards added at once when the queue falls below the low watermark
+    pub work_ahead_batch_size: usize,
+    /// Directory that vocab files given by their bare file name are loaded from
+    pub deck_dir: Option<String>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            max_skips_per_card: None,
+            auto_edit_on_query: false,
+            progress_mode: ProgressMode::default(),
+            study_batch_size: None,
+            multiline_input: false,
+            record_history: false,
+            save_aligned: false,
+            uniform_columns: false,
+            line_ending: LineEnding::default(),
+            encoding: FileEncoding::default(),
+            ctrl_c: CtrlCBehavior::default(),
+            pairing: Pairing::default(),
+            idle_timeout_seconds: 60,
+            warmup_count: 0,
+            show_answer_hints: false,
+            show_readings: true,
+            peek_grading: PeekGrading::default(),
+            think_time_ms: 0,
+            identical_words: IdenticalWords::default(),
+            work_ahead_low_watermark: 0,
+            work_ahead_batch_size: 10,
+            deck_dir: None,
         }
     }
 }
 
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Encoding given by its WHATWG label, e.g. `"utf-8"` or `"windows-1252"`.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(try_from = "String", into = "String")]
+pub struct FileEncoding(pub &'static encoding_rs::Encoding);
+
+impl Default for FileEncoding {
+    fn default() -> Self {
+        FileEncoding(encoding_rs::UTF_8)
+    }
+}
+
+impl TryFrom<String> for FileEncoding {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        // UTF-16 can only be decoded, encoding_rs writes UTF-8 instead
+        encoding_rs::Encoding::for_label(value.as_bytes())
+            .filter(|encoding| encoding.output_encoding() == *encoding)
+            .map(FileEncoding)
+            .ok_or_else(|| format!("Unknown or unsupported encoding '{}'", value))
+    }
+}
+
+impl From<FileEncoding> for String {
+    fn from(encoding: FileEncoding) -> Self {
+        encoding.0.name().to_string()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Pairing {
+    /// All normal directions first, then all reverse directions
+    #[default]
+    Separate,
+    /// The reverse direction of a card right after its normal direction
+    Adjacent,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum IdenticalWords {
+    /// Test both directions like any other card
+    Both,
+    /// Only test the normal direction, the reverse one would be the same task
+    #[default]
+    Forward,
+    /// Leave the card out of sessions
+    Skip,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CtrlCBehavior {
+    /// Ask before discarding unsaved changes
+    #[default]
+    Confirm,
+    /// Quit without saving
+    Quit,
+    SaveAndQuit,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PeekGrading {
+    /// The card fails even if the answer is typed correctly
+    #[default]
+    Wrong,
+    /// A correct answer keeps the card in its deck
+    Hard,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressMode {
+    /// The total is the number of tasks due at the start of the session
+    #[default]
+    Fixed,
+    /// The total grows whenever a wrong card is queued again
+    Dynamic,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DictionaryConfig {
+    /// Program and arguments to look up a card. `%w` is replaced by the query and `%a` by
+    /// the answer
+    pub command: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeechConfig {
+    /// Program and arguments that synthesize the answer of cards without an audio file. `%t`
+    /// is replaced by the text, `%l` by the language and `%o` by the WAV file to write
+    pub command: Vec<String>,
+    /// Keep synthesized answers in `ruvola/speech_cache` next to the config file
+    pub cache: bool,
+    /// Size in megabytes above which the least recently played answers are removed from the
+    /// cache. 0 never removes any
+    pub cache_max_mb: u64,
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            cache: true,
+            cache_max_mb: 100,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct GoalsConfig {
+    /// Number of cards to review per day. Progress and streak are only shown if set
+    pub daily_goal: Option<usize>,
+    /// Maximum number of cards to review per day, counted across all sessions of the day
+    pub daily_cap: Option<usize>,
+    /// Number of recent answers whose accuracy is checked against the early stop thresholds.
+    /// 0 disables it
+    pub early_stop_window: usize,
+    /// Accuracy in percent at or above which stopping the session is suggested
+    pub early_stop_above: Option<u8>,
+    /// Accuracy in percent at or below which a break is suggested
+    pub early_stop_below: Option<u8>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Colors of the prompt title, depending on how long the current card is overdue
+    pub overdue_colors: Vec<OverdueColor>,
+    /// Percentage of the screen height that the answer flash covers
+    pub flash_coverage: FlashCoverage,
+    /// Edge of the screen that the flash starts at if it does not cover the whole screen
+    pub flash_position: FlashPosition,
+    /// How finishing all cards is celebrated
+    pub completion_style: CompletionStyle,
+    /// Color the characters of a reviewed answer by whether they match the closest variant
+    pub highlight_matches: bool,
+    /// Show whether the last reviews were correct as a sparkline next to the progress
+    pub accuracy_sparkline: bool,
+    /// Milliseconds that the answer block takes to slide in once it is revealed. 0 shows it
+    /// right away
+    pub reveal_duration_ms: u64,
+}
+
+impl ThemeConfig {
+    /// Returns the color of the last bucket whose threshold `overdue` has reached.
+    pub fn overdue_color(&self, overdue: Duration) -> Option<Color> {
+        self.overdue_colors
+            .iter()
+            .filter(|bucket| overdue >= bucket.after.0)
+            .max_by_key(|bucket| bucket.after.0)
+            .map(|bucket| bucket.color.0)
+    }
+
+    /// The band of `area` that the answer flash is drawn in.
+    pub fn flash_area(&self, area: Rect) -> Rect {
+        let height = (area.height as u32 * self.flash_coverage.0 as u32).div_ceil(100) as u16;
+        let y = match self.flash_position {
+            FlashPosition::Top => area.y,
+            FlashPosition::Bottom => area.bottom() - height,
+        };
+        Rect { y, height, ..area }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct FlashCoverage(pub u8);
+
+impl Default for FlashCoverage {
+    fn default() -> Self {
+        FlashCoverage(100)
+    }
+}
+
+impl TryFrom<u8> for FlashCoverage {
+    type Error = String;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        if (1..=100).contains(&value) {
+            Ok(FlashCoverage(value))
+        } else {
+            Err(format!(
+                "The flash coverage must be between 1 and 100 percent, got {}",
+                value
+            ))
+        }
+    }
+}
+
+impl From<FlashCoverage> for u8 {
+    fn from(coverage: FlashCoverage) -> Self {
+        coverage.0
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionStyle {
+    /// Only the "No cards left!" screen
+    #[default]
+    None,
+    /// A congratulation above the "No cards left!" title
+    Banner,
+    /// A brief green flash when the last card is done
+    Flash,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashPosition {
+    #[default]
+    Top,
+    Bottom,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OverdueColor {
+    pub after: DeckInverval,
+    pub color: ThemeColor,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(try_from = "String", into = "String")]
+pub struct ThemeColor(pub Color);
+
+impl TryFrom<String> for ThemeColor {
+    type Error = ratatui::style::ParseColorError;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse().map(ThemeColor)
+    }
+}
+
+impl From<ThemeColor> for String {
+    fn from(color: ThemeColor) -> Self {
+        color.0.to_string()
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 #[serde(default, deny_unknown_fields)]
 pub struct ValidationConfig {
     pub error_tolerance: usize,
+    /// Tolerance for the reverse direction. Uses `error_tolerance` if not set
+    pub error_tolerance_reverse: Option<usize>,
     pub tolerance_min_length: usize,
+    /// Languages in which the case of the first letter is not checked
+    pub ignore_leading_case: Vec<String>,
+    /// Number of answers a card accepts before it is graded wrong
+    pub attempts_before_fail: usize,
+    /// Treat runs of the same character as a single one, so that doubled letters are no mistake
+    pub collapse_repeats: bool,
+    /// Letters with diacritics have to be typed, even if the error tolerance would forgive them
+    pub strict_diacritics: bool,
+    /// Languages in which answers that sound like the expected one are accepted
+    pub phonetic_match: Vec<String>,
+    /// Ignore leading and trailing whitespace of answers
+    pub trim_input: bool,
+    /// Treat runs of whitespace in answers as a single space
+    pub collapse_spaces: bool,
+    /// Articles per language that may be left out of answers or added to them
+    pub articles: HashMap<String, Vec<String>>,
+}
+
+impl ValidationConfig {
+    pub fn error_tolerance(&self, reverse: bool) -> usize {
+        if reverse {
+            self.error_tolerance_reverse.unwrap_or(self.error_tolerance)
+        } else {
+            self.error_tolerance
+        }
+    }
 }
 
 impl Default for ValidationConfig {
     fn default() -> Self {
         Self {
             error_tolerance: 2,
+            error_tolerance_reverse: None,
             tolerance_min_length: 5,
+            ignore_leading_case: Vec::new(),
+            attempts_before_fail: 1,
+            collapse_repeats: false,
+            strict_diacritics: false,
+            phonetic_match: Vec::new(),
+            trim_input: true,
+            collapse_spaces: false,
+            articles: HashMap::new(),
         }
     }
 }
@@ -104,12 +628,157 @@ pub struct SpecialLettersConfig {
     pub special: Vec<String>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguagesConfig {
+    /// Warn about deck languages that cannot be resolved to a language code
+    pub warn_unknown: bool,
+    /// Maps the language names used in deck headers to BCP-47 codes
+    pub codes: HashMap<String, String>,
+    /// Codes of languages that are written right to left, in addition to the built-in ones
+    pub rtl: Vec<String>,
+}
+
+/// Primary codes of the languages that are written right to left without any configuration.
+const RTL_LANGUAGES: &[&str] = &["ar", "fa", "he", "ps", "ur", "yi"];
+
+/// Language codes and english names that are recognized without any configuration.
+const KNOWN_LANGUAGES: &[(&str, &str)] = &[
+    ("ar", "arabic"),
+    ("cs", "czech"),
+    ("da", "danish"),
+    ("de", "german"),
+    ("el", "greek"),
+    ("en", "english"),
+    ("es", "spanish"),
+    ("fa", "persian"),
+    ("fi", "finnish"),
+    ("fr", "french"),
+    ("he", "hebrew"),
+    ("hi", "hindi"),
+    ("hu", "hungarian"),
+    ("it", "italian"),
+    ("ja", "japanese"),
+    ("ko", "korean"),
+    ("la", "latin"),
+    ("nl", "dutch"),
+    ("no", "norwegian"),
+    ("pl", "polish"),
+    ("pt", "portuguese"),
+    ("ro", "romanian"),
+    ("ru", "russian"),
+    ("sv", "swedish"),
+    ("tr", "turkish"),
+    ("uk", "ukrainian"),
+    ("zh", "chinese"),
+];
+
+impl LanguagesConfig {
+    /// Resolves a language name from a deck header to a BCP-47 code.
+    ///
+    /// Explicit mappings from the config take precedence over the built-in table, which accepts
+    /// both codes (optionally with a region, e.g. `pt-BR`) and english language names.
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        if let Some(code) = self
+            .codes
+            .iter()
+            .find(|(lang, _)| lang.eq_ignore_ascii_case(name))
+            .map(|(_, code)| code.clone())
+        {
+            return Some(code);
+        }
+        let primary = name.split(['-', '_']).next().unwrap_or(name);
+        KNOWN_LANGUAGES.iter().find_map(|(code, english)| {
+            if code.eq_ignore_ascii_case(primary) {
+                Some(name.replace('_', "-"))
+            } else if english.eq_ignore_ascii_case(name) {
+                Some(code.to_string())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Whether the language with the BCP-47 `code` is written right to left. Configured codes
+    /// match exactly or by their primary subtag, e.g. `ckb` also matches `ckb-IQ`.
+    pub fn is_rtl(&self, code: &str) -> bool {
+        let primary = code.split('-').next().unwrap_or(code);
+        RTL_LANGUAGES
+            .iter()
+            .copied()
+            .chain(self.rtl.iter().map(String::as_str))
+            .any(|rtl| rtl.eq_ignore_ascii_case(code) || rtl.eq_ignore_ascii_case(primary))
+    }
+}
+
+/// Character sequences that are replaced while typing, keyed by language.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Substitutions(pub HashMap<String, Vec<SubstitutionConfig>>);
+
+/// Named groups of words that cards can accept as answers with `@<name>`.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Synonyms(pub HashMap<String, Vec<String>>);
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct SubstitutionConfig {
+    pub trigger: String,
+    pub replacement: String,
+}
+
+/// Settings that replace the global ones for single vocab files, keyed by file name.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeckOverrides(pub HashMap<String, DeckOverride>);
+
+impl DeckOverrides {
+    pub fn get(&self, file_path: &str) -> DeckOverride {
+        let file_name = Path::new(file_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(file_path);
+        self.0.get(file_name).cloned().unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeckOverride {
+    /// Only accept answers without any typos, regardless of the validation config
+    pub exact_match: bool,
+    /// Letters with diacritics have to be typed, even if the error tolerance would forgive them
+    pub strict_diacritics: bool,
+    /// Never test the reverse direction (second to first column) and do not save its progress
+    pub forward_only: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
 #[serde(default, deny_unknown_fields)]
 pub struct DeckConfig {
     #[serde(alias = "deck_durations")]
     pub deck_intervals: Vec<DeckInverval>,
     pub change_deck_in_ignore_date: bool,
+    /// Time after a card was first learned in which wrong answers do not move it below
+    /// `grace_min_deck`. Disabled if not set
+    pub grace_period: Option<DeckInverval>,
+    pub grace_min_deck: u8,
+    /// Factor applied to every deck interval when a card is scheduled
+    pub interval_multiplier: IntervalMultiplier,
+}
+
+impl DeckConfig {
+    /// Interval of `deck`, stretched or compressed by the interval multiplier. Decks beyond the
+    /// last one, e.g. of cards stored before the intervals were shortened, use the last interval.
+    pub fn interval(&self, deck: u8) -> Duration {
+        let last = self.deck_intervals.len().saturating_sub(1);
+        let interval = self
+            .deck_intervals
+            .get((deck as usize).min(last))
+            .map_or(Duration::zero(), |interval| interval.0);
+        let multiplier = self.interval_multiplier.0 as f64;
+        Duration::milliseconds((interval.num_milliseconds() as f64 * multiplier).round() as i64)
+    }
 }
 
 impl Default for DeckConfig {
@@ -120,12 +789,46 @@ impl Default for DeckConfig {
                 .map(|&days| DeckInverval(Duration::days(days)))
                 .collect(),
             change_deck_in_ignore_date: false,
+            grace_period: None,
+            grace_min_deck: 1,
+            interval_multiplier: IntervalMultiplier::default(),
         }
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-#[serde(try_from = "DeckIntervalSer")]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(try_from = "f32", into = "f32")]
+pub struct IntervalMultiplier(pub f32);
+
+impl Default for IntervalMultiplier {
+    fn default() -> Self {
+        IntervalMultiplier(1.0)
+    }
+}
+
+impl TryFrom<f32> for IntervalMultiplier {
+    type Error = String;
+
+    fn try_from(value: f32) -> std::result::Result<Self, Self::Error> {
+        if value.is_finite() && value > 0.0 {
+            Ok(IntervalMultiplier(value))
+        } else {
+            Err(format!(
+                "The interval multiplier must be positive, got {}",
+                value
+            ))
+        }
+    }
+}
+
+impl From<IntervalMultiplier> for f32 {
+    fn from(multiplier: IntervalMultiplier) -> Self {
+        multiplier.0
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(try_from = "DeckIntervalSer", into = "DeckIntervalSer")]
 pub struct DeckInverval(pub Duration);
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
@@ -136,10 +839,10 @@ enum DeckIntervalSer {
 }
 
 #[derive(Debug)]
-enum IntervalParseError {
+pub enum IntervalParseError {
     InvalidFormat,
     InvalidNumber,
-    InvalidUnit,
+    InvalidUnit(char),
     ExpectedDigit,
 }
 impl std::fmt::Display for IntervalParseError {
@@ -147,7 +850,11 @@ impl std::fmt::Display for IntervalParseError {
         match self {
             IntervalParseError::InvalidFormat => write!(f, "Invalid format"),
             IntervalParseError::InvalidNumber => write!(f, "Invalid number"),
-            IntervalParseError::InvalidUnit => write!(f, "Invalid unit"),
+            IntervalParseError::InvalidUnit(unit) => write!(
+                f,
+                "Invalid unit '{}', expected one of y, M, w, d, h, m or s",
+                unit
+            ),
             IntervalParseError::ExpectedDigit => write!(f, "Expected digit"),
         }
     }
@@ -168,7 +875,40 @@ impl TryFrom<DeckIntervalSer> for DeckInverval {
     }
 }
 
-fn parse_complex_duration(complex: &str) -> Result<Duration, IntervalParseError> {
+impl From<DeckInverval> for DeckIntervalSer {
+    fn from(interval: DeckInverval) -> Self {
+        let duration = interval.0;
+        if duration == Duration::days(duration.num_days()) {
+            DeckIntervalSer::Days(duration.num_days() as u32)
+        } else {
+            DeckIntervalSer::Complex(format_complex_duration(duration))
+        }
+    }
+}
+
+/// Formats `duration` in the format read by `parse_complex_duration`, e.g. `1d12h`. Parts
+/// below a second are dropped.
+pub fn format_complex_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds();
+    let parts = [
+        (seconds / 86400, 'd'),
+        (seconds / 3600 % 24, 'h'),
+        (seconds / 60 % 60, 'm'),
+        (seconds % 60, 's'),
+    ];
+    let formatted = parts
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect::<String>();
+    if formatted.is_empty() {
+        "0s".to_string()
+    } else {
+        formatted
+    }
+}
+
+pub fn parse_complex_duration(complex: &str) -> Result<Duration, IntervalParseError> {
     let mut current_duration = Duration::zero();
     let mut current_number = Vec::new();
     for c in complex.chars() {
@@ -184,13 +924,22 @@ fn parse_complex_duration(complex: &str) -> Result<Duration, IntervalParseError>
             .collect::<String>()
             .parse()
             .map_err(|_| IntervalParseError::InvalidNumber)?;
-        current_duration += match c {
-            'd' => Duration::days(total_number as i64),
-            'h' => Duration::hours(total_number as i64),
-            'm' => Duration::minutes(total_number as i64),
-            's' => Duration::seconds(total_number as i64),
-            _ => return Err(IntervalParseError::InvalidUnit),
+        let total_number = total_number as i64;
+        // Months and years have a fixed length, like all deck intervals
+        let duration = match c {
+            'y' => Duration::try_days(365 * total_number),
+            'M' => Duration::try_days(30 * total_number),
+            'w' => Duration::try_weeks(total_number),
+            'd' => Duration::try_days(total_number),
+            'h' => Duration::try_hours(total_number),
+            'm' => Duration::try_minutes(total_number),
+            's' => Duration::try_seconds(total_number),
+            _ => return Err(IntervalParseError::InvalidUnit(c)),
         };
+        // Numbers too large for a duration would otherwise panic
+        current_duration = duration
+            .and_then(|duration| current_duration.checked_add(&duration))
+            .ok_or(IntervalParseError::InvalidNumber)?;
         current_number.clear();
     }
     if current_number.is_empty() {
@@ -217,20 +966,20 @@ fn deep_override_config(base: toml::Value, override_config: toml::Value) -> toml
 }
 
 #[cfg(target_os = "linux")]
-fn get_system_config_dir() -> Result<String, std::env::VarError> {
+pub fn get_system_config_dir() -> Result<String, std::env::VarError> {
     let config_dir = std::env::var("XDG_CONFIG_HOME")
         .or_else(|_| std::env::var("HOME").map(|home| format!("{}/.config", home)))?;
     Ok(config_dir)
 }
 
 #[cfg(target_os = "windows")]
-fn get_system_config_dir() -> Result<String, std::env::VarError> {
+pub fn get_system_config_dir() -> Result<String, std::env::VarError> {
     let config_dir = std::env::var("APPDATA")?;
     Ok(config_dir)
 }
 
 #[cfg(target_os = "macos")]
-fn get_system_config_dir() -> Result<String, std::env::VarError> {
+pub fn get_system_config_dir() -> Result<String, std::env::VarError> {
     let config_dir =
         std::env::var("HOME").map(|home| format!("{}/Library/Application Support", home))?;
     Ok(config_dir)
@@ -275,18 +1024,140 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn env_overrides_test() {
+        let vars = [
+            ("RUVOLA_VALIDATION_ERROR_TOLERANCE", "1"),
+            ("RUVOLA_DECK_CONFIG_DECK_INTERVALS", "['1d', '3d']"),
+            ("RUVOLA_KEYBINDINGS_SKIP", "n"),
+            ("RUVOLA_LOG", "debug"),
+            ("HOME", "/home/user"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let expected: toml::Value = toml! {
+            [validation]
+            error_tolerance = 1
+
+            [deck_config]
+            deck_intervals = ["1d", "3d"]
+
+            [keybindings]
+            skip = "n"
+        }
+        .into();
+        assert_eq!(env_overrides(vars.into_iter()), expected);
+    }
+
+    #[test]
+    fn overdue_color_buckets() {
+        let theme: ThemeConfig = toml::de::from_str(
+            r#"
+            overdue_colors = [
+                { after = "7d", color = "red" },
+                { after = 0, color = "yellow" },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(theme.overdue_color(Duration::hours(-1)), None);
+        assert_eq!(theme.overdue_color(Duration::hours(5)), Some(Color::Yellow));
+        assert_eq!(theme.overdue_color(Duration::days(30)), Some(Color::Red));
+        assert_eq!(
+            ThemeConfig::default().overdue_color(Duration::days(30)),
+            None
+        );
+    }
+
+    #[test]
+    fn flash_band() {
+        let area = Rect::new(0, 0, 80, 25);
+        assert_eq!(ThemeConfig::default().flash_area(area), area);
+        let theme: ThemeConfig = toml::de::from_str(
+            r#"
+            flash_coverage = 20
+            flash_position = "bottom"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(theme.flash_area(area), Rect::new(0, 20, 80, 5));
+        assert!(toml::de::from_str::<ThemeConfig>("flash_coverage = 0").is_err());
+        assert!(toml::de::from_str::<ThemeConfig>("flash_coverage = 150").is_err());
+    }
+
+    #[test]
+    fn deck_override_by_file_name() {
+        let overrides: DeckOverrides = toml::de::from_str(
+            r#"
+            "keywords.tsv" = { exact_match = true }
+            "#,
+        )
+        .unwrap();
+        assert!(overrides.get("decks/keywords.tsv").exact_match);
+        assert!(!overrides.get("decks/other.tsv").exact_match);
+    }
+
     #[test]
     fn validate_config_preset() {
         let config: AppConfig =
             toml::de::from_str(&std::fs::read_to_string("config_preset/config.toml").unwrap())
                 .unwrap();
-        assert_eq!(config.memorization.do_memorization_round, true);
-        assert_eq!(config.memorization.memorization_reversed, false);
+        assert!(config.memorization.do_memorization_round);
+        assert!(!config.memorization.memorization_reversed);
+        assert_eq!(config.memorization.memorization_min_new_cards, 1);
+        assert_eq!(config.session.progress_mode, ProgressMode::Fixed);
         assert_eq!(config.validation.error_tolerance, 2);
         assert_eq!(config.validation.tolerance_min_length, 5);
         assert_eq!(config.special_letters.0.len(), 3);
     }
 
+    #[test]
+    fn resolve_language_codes() {
+        let mut languages = LanguagesConfig::default();
+        languages
+            .codes
+            .insert("Deutsch".to_string(), "de-DE".to_string());
+        assert_eq!(languages.resolve("de"), Some("de".to_string()));
+        assert_eq!(languages.resolve("pt_BR"), Some("pt-BR".to_string()));
+        assert_eq!(languages.resolve("French"), Some("fr".to_string()));
+        assert_eq!(languages.resolve("deutsch"), Some("de-DE".to_string()));
+        assert_eq!(languages.resolve("Klingon"), None);
+    }
+
+    #[test]
+    fn rtl_languages() {
+        let mut languages = LanguagesConfig::default();
+        assert!(languages.is_rtl("he"));
+        assert!(languages.is_rtl("ar-EG"));
+        assert!(!languages.is_rtl("de"));
+        assert!(!languages.is_rtl("ckb-IQ"));
+        languages.rtl.push("ckb".to_string());
+        assert!(languages.is_rtl("ckb-IQ"));
+    }
+
+    #[test]
+    fn file_encoding() {
+        let session: SessionConfig = toml::de::from_str("encoding = \"latin1\"").unwrap();
+        assert_eq!(session.encoding.0, encoding_rs::WINDOWS_1252);
+        assert!(toml::de::from_str::<SessionConfig>("encoding = \"utf-16le\"").is_err());
+        assert!(toml::de::from_str::<SessionConfig>("encoding = \"klingon\"").is_err());
+    }
+
+    #[test]
+    fn interval_multiplier() {
+        let deck_config: DeckConfig =
+            toml::de::from_str("deck_intervals = [0, \"12h\", 2]\ninterval_multiplier = 1.5")
+                .unwrap();
+        assert_eq!(deck_config.interval(0), Duration::zero());
+        assert_eq!(deck_config.interval(1), Duration::hours(18));
+        assert_eq!(deck_config.interval(2), Duration::days(3));
+        assert_eq!(deck_config.interval(7), Duration::days(3));
+        // The stored intervals are not changed
+        assert_eq!(deck_config.deck_intervals[2].0, Duration::days(2));
+        assert!(toml::de::from_str::<DeckConfig>("interval_multiplier = 2").is_ok());
+        assert!(toml::de::from_str::<DeckConfig>("interval_multiplier = 0").is_err());
+        assert!(toml::de::from_str::<DeckConfig>("interval_multiplier = -1.0").is_err());
+    }
+
     #[test]
     fn system_config_dir() {
         assert!(fs::exists(get_system_config_dir().unwrap()).unwrap());
@@ -320,6 +1191,73 @@ mod tests {
         let invalid = parse_complex_duration("1d2h3m4");
         assert!(invalid.is_err());
         let invalid = parse_complex_duration("1d2h3m4x");
-        assert!(invalid.is_err());
+        assert!(matches!(invalid, Err(IntervalParseError::InvalidUnit('x'))));
+
+        assert_eq!(parse_complex_duration("2w").unwrap(), Duration::days(14));
+        assert_eq!(parse_complex_duration("1y").unwrap(), Duration::days(365));
+        assert_eq!(parse_complex_duration("3M").unwrap(), Duration::days(90));
+        assert_eq!(
+            parse_complex_duration("1y2M1w3d12h30m").unwrap(),
+            Duration::days(365 + 60 + 7 + 3) + Duration::hours(12) + Duration::minutes(30)
+        );
+        assert!(matches!(
+            parse_complex_duration("4000000000y"),
+            Err(IntervalParseError::InvalidNumber)
+        ));
+        assert!(matches!(
+            parse_complex_duration(&"4000000000w".repeat(4)),
+            Err(IntervalParseError::InvalidNumber)
+        ));
+        let interval: DeckInverval = toml::Value::from("2w").try_into().unwrap();
+        assert_eq!(interval, DeckInverval(Duration::weeks(2)));
+    }
+
+    #[test]
+    fn json_config_file() {
+        let path = std::env::temp_dir().join(format!("ruvola-config-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"validation": {"error_tolerance": 0}, "deck_config": {"deck_intervals": [0, "12h", 3]}}"#,
+        )
+        .unwrap();
+        let value = read_config_value(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        let config: AppConfig = deep_override_config(
+            toml::toml! {
+                [validation]
+                error_tolerance = 2
+                tolerance_min_length = 3
+            }
+            .into(),
+            value.unwrap(),
+        )
+        .try_into()
+        .unwrap();
+        assert_eq!(config.validation.error_tolerance, 0);
+        assert_eq!(config.validation.tolerance_min_length, 3);
+        assert_eq!(
+            config.deck_config.deck_intervals[1],
+            DeckInverval(Duration::hours(12))
+        );
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let preset: AppConfig =
+            toml::de::from_str(&std::fs::read_to_string("config_preset/config.toml").unwrap())
+                .unwrap();
+        for config in [AppConfig::default(), preset] {
+            let serialized = toml::to_string(&config).unwrap();
+            let parsed: AppConfig = toml::de::from_str(&serialized).unwrap();
+            assert_eq!(parsed, config);
+        }
+
+        let interval = Duration::days(1) + Duration::hours(12) + Duration::seconds(5);
+        assert_eq!(format_complex_duration(interval), "1d12h5s");
+        assert_eq!(
+            parse_complex_duration(&format_complex_duration(interval)).unwrap(),
+            interval
+        );
+        assert_eq!(format_complex_duration(Duration::zero()), "0s");
     }
 }
@@ -0,0 +1,106 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use crate::model::{voca_card::DATE_FORMAT, voca_session::ReviewOutcome};
+
+const HEADER: [&str; 9] = [
+    "timestamp",
+    "file",
+    "query",
+    "answer",
+    "my_input",
+    "correct",
+    "old_deck",
+    "new_deck",
+    "direction",
+];
+
+/// Appends one CSV row per review to a file that grows across sessions.
+pub struct CsvLog {
+    writer: BufWriter<File>,
+}
+
+impl CsvLog {
+    /// Opens `path` for appending and writes the header if the file is new.
+    pub fn open(path: &str) -> Result<Self, std::io::Error> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut log = CsvLog {
+            writer: BufWriter::new(file),
+        };
+        if is_empty {
+            log.write_row(&HEADER)?;
+        }
+        Ok(log)
+    }
+
+    pub fn log_review(
+        &mut self,
+        outcome: &ReviewOutcome,
+        input: &str,
+        correct: bool,
+    ) -> Result<(), std::io::Error> {
+        let timestamp = chrono::Local::now()
+            .naive_utc()
+            .format(DATE_FORMAT)
+            .to_string();
+        let old_deck = outcome
+            .old_deck
+            .map(|deck| deck.to_string())
+            .unwrap_or_default();
+        self.write_row(&[
+            &timestamp,
+            &outcome.file_path,
+            &outcome.query,
+            &outcome.answer,
+            input,
+            if correct { "true" } else { "false" },
+            &old_deck,
+            &outcome.new_deck.to_string(),
+            if outcome.reverse {
+                "reverse"
+            } else {
+                "forward"
+            },
+        ])
+    }
+
+    fn write_row(&mut self, fields: &[&str]) -> Result<(), std::io::Error> {
+        let row = fields
+            .iter()
+            .map(|field| escape(field))
+            .collect::<Vec<_>>()
+            .join(",");
+        // Flush every row so that nothing is lost if the session ends abruptly
+        writeln!(self.writer, "{}", row)?;
+        self.writer.flush()
+    }
+}
+
+/// Quotes `field` as described in RFC 4180 if it contains a separator, quote or line break.
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_fields() {
+        assert_eq!(escape("hola"), "hola");
+        assert_eq!(escape("hola, buenos días"), "\"hola, buenos días\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+
+use crate::config::GoalsConfig;
+
+/// Why stopping the session early is suggested.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EarlyStop {
+    /// The accuracy reached the upper threshold, the cards are known well today
+    Mastered,
+    /// The accuracy fell to the lower threshold, probably from being tired
+    Tired,
+}
+
+/// Tracks the accuracy of the last answers to suggest stopping once it crosses a threshold.
+pub struct EarlyStopTracker {
+    window_size: usize,
+    /// Accuracy in percent at or above which stopping is suggested
+    above: Option<u8>,
+    /// Accuracy in percent at or below which stopping is suggested
+    below: Option<u8>,
+    recent: VecDeque<bool>,
+}
+
+impl EarlyStopTracker {
+    pub fn new(goals: &GoalsConfig) -> Self {
+        EarlyStopTracker {
+            window_size: goals.early_stop_window,
+            above: goals.early_stop_above,
+            below: goals.early_stop_below,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Records an answer. Once the window is full and its accuracy crosses a threshold, the
+    /// window starts over so that the suggestion is not repeated with every answer.
+    pub fn record(&mut self, correct: bool) -> Option<EarlyStop> {
+        if self.window_size == 0 {
+            return None;
+        }
+        self.recent.push_back(correct);
+        if self.recent.len() > self.window_size {
+            self.recent.pop_front();
+        }
+        if self.recent.len() < self.window_size {
+            return None;
+        }
+        let correct = self.recent.iter().filter(|&&correct| correct).count();
+        let accuracy = correct * 100 / self.window_size;
+        let stop = if self.above.is_some_and(|above| accuracy >= above as usize) {
+            EarlyStop::Mastered
+        } else if self.below.is_some_and(|below| accuracy <= below as usize) {
+            EarlyStop::Tired
+        } else {
+            return None;
+        };
+        self.recent.clear();
+        Some(stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds() {
+        let mut tracker = EarlyStopTracker::new(&GoalsConfig {
+            early_stop_window: 4,
+            early_stop_above: Some(100),
+            early_stop_below: Some(25),
+            ..Default::default()
+        });
+        assert_eq!(tracker.record(true), None);
+        assert_eq!(tracker.record(false), None);
+        assert_eq!(tracker.record(true), None);
+        // 3 of 4
+        assert_eq!(tracker.record(true), None);
+        assert_eq!(tracker.record(true), None);
+        assert_eq!(tracker.record(true), Some(EarlyStop::Mastered));
+        // The window starts over
+        assert_eq!(tracker.record(true), None);
+        for _ in 0..2 {
+            assert_eq!(tracker.record(false), None);
+        }
+        assert_eq!(tracker.record(false), Some(EarlyStop::Tired));
+
+        let mut disabled = EarlyStopTracker::new(&GoalsConfig::default());
+        assert!((0..10).all(|_| disabled.record(true).is_none()));
+    }
+}
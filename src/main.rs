@@ -1,45 +1,232 @@
-use anyhow::Result;
-use clap::Parser;
-use config::AppConfig;
-use crossterm::execute;
-use model::voca_session::VocaSession;
+use anyhow::{Context, Result};
+use audio::AudioPlayer;
+use clap::{CommandFactory, Parser};
+use config::{AppConfig, CompletionStyle, CtrlCBehavior, PeekGrading, SubstitutionConfig};
+use crossterm::{
+    event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    execute,
+};
+use csv_log::CsvLog;
+use early_stop::{EarlyStop, EarlyStopTracker};
+use model::{
+    alignment::matched_chars,
+    review_history::ReviewEntry,
+    voca_card::{VocaCardDataset, VocaParseError, is_url},
+    voca_session::{ChangeSummary, DeckHealth, Grade, VocaSession, VocabTask, split_read_only},
+};
 use ratatui::{
     DefaultTerminal, Frame,
     crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
-    layout::{Constraint, Flex, Layout, Position},
+    layout::{Constraint, Flex, Layout, Position, Rect},
     style::{Color, Style, Stylize},
     symbols::Marker,
     text::{Line, Span, Text},
     widgets::{
-        Block, Clear, List, Padding, Paragraph, Row, Table, Widget, Wrap,
+        Block, Clear, List, Padding, Paragraph, Row, Sparkline, Table, Widget, Wrap,
         canvas::{Canvas, Rectangle},
     },
 };
+use state::AppState;
+#[cfg(feature = "audio")]
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::{
+    collections::VecDeque,
+    io::Write,
+    ops::RangeInclusive,
+    path::PathBuf,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+use timer::SessionTimer;
 
+mod audio;
+mod commands;
 mod config;
+mod csv_log;
+mod early_stop;
 mod model;
+// Speech is only played with the audio feature, but its cache is tested without it as well
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+mod speech;
+mod state;
+mod timer;
+
+/// Exit code when no cards were due at the start of the session
+const EXIT_NOTHING_DUE: u8 = 3;
+/// Exit code when a config or vocab file could not be parsed
+const EXIT_INVALID_INPUT: u8 = 4;
+/// How long the screen flashes once all cards are done, with `completion_style = "flash"`
+const COMPLETION_FLASH_DURATION: Duration = Duration::from_secs(1);
+/// Number of recent review outcomes shown in the accuracy sparkline
+const SPARKLINE_OUTCOMES: usize = 20;
+/// Reviews needed before the accuracy sparkline is shown
+const SPARKLINE_MIN_OUTCOMES: usize = 5;
+/// Time between the frames of the answer reveal animation
+const REVEAL_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+/// How often the speech command is checked for its result while it runs
+#[cfg(feature = "audio")]
+const SPEECH_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(error_exit_code(&err))
+        }
+    }
+}
 
-fn main() -> Result<()> {
-    let args = Arguments::parse();
+/// Exit code for an error that ended the program, `EXIT_INVALID_INPUT` if a config or vocab
+/// file could not be parsed.
+fn error_exit_code(err: &anyhow::Error) -> u8 {
+    if err.chain().any(|e| {
+        e.is::<VocaParseError>() || e.is::<toml::de::Error>() || e.is::<serde_json::Error>()
+    }) {
+        EXIT_INVALID_INPUT
+    } else {
+        1
+    }
+}
+
+fn run() -> Result<ExitCode> {
+    let mut args = Arguments::parse();
     cli_log::init_cli_log!();
     let config = config::AppConfig::load_from_config_file(args.override_config_file.as_deref())?;
-    let session = VocaSession::from_files(
-        &args.file_paths,
-        (&args).try_into()?,
-        (&args).try_into()?,
-        args.limit,
-        &config.memorization,
-    )?;
-    let mut terminal = ratatui::init();
-    // Set cursor style to steady bar
-    execute!(
-        terminal.backend_mut(),
-        crossterm::cursor::SetCursorStyle::SteadyBar
-    )?;
+    if let Some(command) = &args.command {
+        command.run(&config)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(deck_dir) = &config.session.deck_dir {
+        resolve_deck_paths(&mut args.file_paths, deck_dir, !args.create)?;
+        resolve_deck_paths(&mut args.read_only, deck_dir, !args.create)?;
+    }
+    if args.create {
+        create_missing_files(&args.file_paths)?;
+    }
+    let state = AppState::load()?;
+    // Conflicting flags are usage errors, reported like the ones that clap finds itself
+    let mut options: SessionOptions = (&args).try_into().unwrap_or_else(|err: anyhow::Error| {
+        Arguments::command()
+            .error(clap::error::ErrorKind::ArgumentConflict, err)
+            .exit()
+    });
+    options.review_cap = config
+        .goals
+        .daily_cap
+        .map(|cap| cap.saturating_sub(state.reviews_on(chrono::Local::now().date_naive())));
+    options.new_limit = args
+        .new_limit
+        .map(|limit| limit.saturating_sub(state.new_cards_on(chrono::Local::now().date_naive())));
+    let session = VocaSession::from_files(&args.file_paths, &options, &config)?;
+    if let Some(text) = &args.grep
+        && !session.any_card_matches(&text.to_lowercase())
+    {
+        anyhow::bail!("No cards match '{}'", text);
+    }
+    let nothing_due = session.total_tasks() == 0;
+    // Shift+Enter can only be told apart from Enter with the keyboard enhancement protocol
+    let enhance_keyboard = config.session.multiline_input
+        && crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false);
+    let csv_log = args
+        .log_csv
+        .as_deref()
+        .map(|path| {
+            CsvLog::open(path).with_context(|| format!("Could not open the CSV log '{}'", path))
+        })
+        .transpose()?;
+    let app = App::new(
+        config,
+        session,
+        state,
+        (&args).into(),
+        args.new_limit,
+        csv_log,
+        enhance_keyboard,
+    );
 
-    let app_result = App::new(config, session).run(terminal);
+    let mut terminal = ratatui::init();
+    // The terminal has to be restored on every exit path, so errors are only returned afterwards
+    let app_result = (|| {
+        // Set cursor style to steady bar
+        execute!(
+            terminal.backend_mut(),
+            crossterm::cursor::SetCursorStyle::SteadyBar
+        )?;
+        if enhance_keyboard {
+            execute!(
+                terminal.backend_mut(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            )?;
+        }
+        app.run(terminal)
+    })();
+    // A failed pop must neither keep the terminal in raw mode nor hide the app's result
+    if enhance_keyboard && let Err(err) = execute!(std::io::stdout(), PopKeyboardEnhancementFlags) {
+        log::warn!("Failed to pop the keyboard enhancement flags: {}", err);
+    }
     ratatui::restore();
-    app_result
+    app_result?;
+    Ok(if nothing_due {
+        ExitCode::from(EXIT_NOTHING_DUE)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Resolves bare file names, i.e. paths without a `/`, relative to `deck_dir`. With
+/// `must_exist`, a file that is not found in the deck directory is an error.
+fn resolve_deck_paths(file_paths: &mut [String], deck_dir: &str, must_exist: bool) -> Result<()> {
+    for file_path in file_paths {
+        let (name, _) = split_read_only(file_path);
+        if name.contains('/') {
+            continue;
+        }
+        let resolved = std::path::Path::new(deck_dir).join(name);
+        if must_exist && !resolved.exists() {
+            anyhow::bail!(
+                "'{}' was not found in the deck directory '{}'",
+                name,
+                deck_dir
+            );
+        }
+        // Keeps a read-only suffix
+        *file_path = format!("{}{}", resolved.display(), &file_path[name.len()..]);
+    }
+    Ok(())
+}
+
+/// Writes a header-only vocab file for every path that does not exist yet.
+/// The language names are read from stdin.
+fn create_missing_files(file_paths: &[String]) -> Result<()> {
+    for file_path in file_paths {
+        let (file_path, _) = split_read_only(file_path);
+        if is_url(file_path) || std::path::Path::new(file_path).exists() {
+            continue;
+        }
+        println!("Creating '{}'", file_path);
+        let lang_a = prompt("Name of the first language: ")?;
+        let lang_b = prompt("Name of the second language: ")?;
+        VocaCardDataset::new(file_path, &lang_a, &lang_b)
+            .save()
+            .with_context(|| format!("Could not create '{}'", file_path))?;
+    }
+    Ok(())
+}
+
+fn prompt(message: &str) -> Result<String> {
+    loop {
+        print!("{}", message);
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            anyhow::bail!("Unexpected end of input");
+        }
+        let line = line.trim();
+        if !line.is_empty() && !line.contains('\t') {
+            return Ok(line.to_string());
+        }
+    }
 }
 
 #[derive(clap::Parser, Debug)]
@@ -49,6 +236,10 @@ struct Arguments {
     /// may be higher since both directions are tested and a potential memorization round.
     #[arg(short, long)]
     limit: Option<usize>,
+    /// Limit for the number of new cards introduced per day, shared by all vocab files and
+    /// sessions of the day
+    #[arg(long)]
+    new_limit: Option<usize>,
     /// Show all cards, even if they are not due
     #[arg(short, long)]
     ignore_date: bool,
@@ -64,17 +255,206 @@ struct Arguments {
     /// Use the random order for the cards
     #[arg(short, long)]
     random: bool,
+    /// Order in which the cards are studied
+    #[arg(long, value_enum)]
+    sort_by: Option<SortMode>,
+    /// Study this many randomly picked cards, regardless of their due dates
+    #[arg(long, conflicts_with_all = ["only_seen", "only_unseen"])]
+    random_subset: Option<usize>,
+    /// Only study cards whose words or hint contain this text, ignoring case
+    #[arg(long, value_name = "TEXT")]
+    grep: Option<String>,
+    /// Seed for all random choices, making them reproducible
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Only study cards in these decks, e.g. `0..2`, `1..=3` or `4..`
+    #[arg(long, value_parser = parse_deck_range)]
+    deck_range: Option<RangeInclusive<u8>>,
+    /// Offer to load the cards due within this many days once no card is left
+    #[arg(long)]
+    work_ahead: Option<u32>,
+    /// Test only one direction per card, favoring the direction in the lower deck
+    #[arg(long)]
+    weighted_direction: bool,
+    /// Spread out the cards of each vocab file instead of studying the files one after another
+    #[arg(long)]
+    spread_related: bool,
+    /// Show each card on a single line without borders or the full-screen flash
+    #[arg(long)]
+    compact: bool,
+    /// Reveal the answer without typing it and grade yourself
+    #[arg(long, conflicts_with = "compact")]
+    recognition: bool,
+    /// Append a row for every answered card to this CSV file
+    #[arg(long)]
+    log_csv: Option<String>,
+    /// Create vocab files that do not exist yet, asking for their language names
+    #[arg(long)]
+    create: bool,
+    /// Directory to save the progress of vocab files loaded from URLs to. Without it, they are
+    /// read-only
+    #[arg(long)]
+    save_to: Option<PathBuf>,
+    /// Vocab file whose progress is never saved, e.g. a shared reference deck. Can be given
+    /// multiple times. Appending `:ro` to a file path does the same
+    #[arg(long, value_name = "FILE")]
+    read_only: Vec<String>,
     /// Path to a local config file that overrides attributes of the global config file
     #[arg(long)]
     override_config_file: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Paths to the vocab files
     file_paths: Vec<String>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Move learned cards out of the vocab files into archive files
+    Archive {
+        /// Minimum deck in both directions for a card to be archived. Defaults to the last deck
+        #[arg(long)]
+        min_deck: Option<u8>,
+        /// Archive file to use instead of `<file>.archive`. Only valid for a single vocab file
+        #[arg(long)]
+        archive_file: Option<String>,
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+    /// Move all archived cards back into their vocab files
+    Unarchive {
+        /// Archive file to use instead of `<file>.archive`. Only valid for a single vocab file
+        #[arg(long)]
+        archive_file: Option<String>,
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+    /// Spread the due dates of all overdue cards over the coming days
+    Postpone {
+        /// Time span to spread the overdue cards over, e.g. `30d` or `7d12h`
+        #[arg(long, value_parser = config::parse_complex_duration)]
+        spread: chrono::Duration,
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+    /// Make all cards unseen again, keeping the words
+    Reset {
+        /// Keep the decks and only make all scheduled cards due now
+        #[arg(long)]
+        keep_decks: bool,
+        /// Do not ask for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+    /// List the cards that are due on or before a date
+    Due {
+        /// Date in the format `YYYY-MM-DD`
+        #[arg(long, value_parser = parse_date)]
+        on: chrono::NaiveDate,
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+    /// Copy the cards that are due now into a new vocab file, keeping their progress
+    ExtractDue {
+        /// Path of the new vocab file
+        #[arg(long)]
+        out: String,
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+    /// Write a printable HTML study sheet for each vocab file
+    ExportSheet {
+        /// Output file to use instead of `<file>.html`. Only valid for a single vocab file
+        #[arg(long)]
+        output: Option<String>,
+        /// Group the cards by their deck
+        #[arg(long)]
+        group_by_deck: bool,
+        /// Leave the answer column empty for self-testing
+        #[arg(long)]
+        hide_answers: bool,
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+    /// Check whether typed answers would be accepted, without starting a session
+    TestAnswers {
+        /// TSV file with a query and a typed answer per line
+        #[arg(long)]
+        input: String,
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+    /// Print the effective configuration as TOML
+    DumpConfig,
+    /// Check that saving the vocab files would not change them
+    Verify {
+        /// Paths to the vocab files
+        file_paths: Vec<String>,
+    },
+}
+
+impl Command {
+    fn run(&self, config: &AppConfig) -> Result<()> {
+        match self {
+            Command::Archive {
+                min_deck,
+                archive_file,
+                file_paths,
+            } => {
+                let last_deck = config.deck_config.deck_intervals.len().saturating_sub(1) as u8;
+                commands::archive::archive(
+                    file_paths,
+                    archive_file.as_deref(),
+                    min_deck.unwrap_or(last_deck),
+                    config,
+                )
+            }
+            Command::Unarchive {
+                archive_file,
+                file_paths,
+            } => commands::archive::unarchive(file_paths, archive_file.as_deref(), config),
+            Command::Postpone { spread, file_paths } => {
+                commands::postpone::postpone(file_paths, *spread, &config.session)
+            }
+            Command::Reset {
+                keep_decks,
+                yes,
+                file_paths,
+            } => commands::reset::reset(file_paths, *keep_decks, *yes, config),
+            Command::Due { on, file_paths } => commands::due::due(file_paths, *on, config),
+            Command::ExtractDue { out, file_paths } => {
+                commands::extract_due::extract_due(file_paths, out, config)
+            }
+            Command::ExportSheet {
+                output,
+                group_by_deck,
+                hide_answers,
+                file_paths,
+            } => commands::export_sheet::export_sheet(
+                file_paths,
+                output.as_deref(),
+                *group_by_deck,
+                *hide_answers,
+                config.session.encoding,
+            ),
+            Command::TestAnswers { input, file_paths } => {
+                commands::test_answers::test_answers(file_paths, input, config)
+            }
+            Command::DumpConfig => commands::dump_config::dump_config(config),
+            Command::Verify { file_paths } => commands::verify::verify(file_paths, config),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
 pub enum SortMode {
     DueDate,
     Random,
+    /// Cards that were not reviewed for the longest time first
+    LastSeen,
+    #[default]
     Original,
 }
 
@@ -82,12 +462,19 @@ impl TryFrom<&Arguments> for SortMode {
     type Error = anyhow::Error;
 
     fn try_from(args: &Arguments) -> Result<Self> {
-        if args.sort && args.random {
+        if [args.sort, args.random, args.sort_by.is_some()]
+            .iter()
+            .filter(|&&x| x)
+            .count()
+            > 1
+        {
             return Err(anyhow::anyhow!(
-                "Cannot sort by due date and random order at the same time"
+                "Only one of --sort, --random, or --sort-by can be specified"
             ));
         }
-        Ok(if args.sort {
+        Ok(if let Some(sort_mode) = args.sort_by {
+            sort_mode
+        } else if args.sort {
             SortMode::DueDate
         } else if args.random {
             SortMode::Random
@@ -97,8 +484,9 @@ impl TryFrom<&Arguments> for SortMode {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum FilterMode {
+    #[default]
     Normal,
     All,
     Seen,
@@ -131,6 +519,101 @@ impl TryFrom<&Arguments> for FilterMode {
     }
 }
 
+/// Options controlling which cards are part of a session and in which order.
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    pub filter_mode: FilterMode,
+    pub sort_mode: SortMode,
+    pub limit: Option<usize>,
+    pub random_subset: Option<usize>,
+    /// Text that the words or the hint of a card have to contain, in lowercase
+    pub grep: Option<String>,
+    pub seed: Option<u64>,
+    pub weighted_direction: bool,
+    /// Interleave the cards so that cards of the same file are as far apart as possible
+    pub spread_related: bool,
+    pub deck_range: Option<RangeInclusive<u8>>,
+    /// How far ahead cards can be loaded once nothing is due anymore
+    pub work_ahead: Option<chrono::Duration>,
+    /// Directory that vocab files loaded from URLs are saved to
+    pub save_to: Option<PathBuf>,
+    /// Vocab files whose progress is never saved
+    pub read_only: Vec<String>,
+    /// Maximum number of directions to test, what is left of the daily cap
+    pub review_cap: Option<usize>,
+    /// Maximum number of new cards of all files, what is left of the daily new card limit
+    pub new_limit: Option<usize>,
+}
+
+impl TryFrom<&Arguments> for SessionOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(args: &Arguments) -> Result<Self> {
+        Ok(SessionOptions {
+            filter_mode: args.try_into()?,
+            sort_mode: args.try_into()?,
+            limit: args.limit,
+            random_subset: args.random_subset,
+            grep: args.grep.as_ref().map(|text| text.to_lowercase()),
+            seed: args.seed,
+            weighted_direction: args.weighted_direction,
+            spread_related: args.spread_related,
+            deck_range: args.deck_range.clone(),
+            work_ahead: args
+                .work_ahead
+                .map(|days| chrono::Duration::days(days as i64)),
+            save_to: args.save_to.clone(),
+            read_only: args.read_only.clone(),
+            review_cap: None,
+            new_limit: None,
+        })
+    }
+}
+
+fn parse_date(date: &str) -> Result<chrono::NaiveDate, chrono::ParseError> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+}
+
+/// Parses a deck range in Rust range syntax (`a..b`, `a..=b`, `a..`, `..b`) into an inclusive range.
+fn parse_deck_range(range: &str) -> Result<RangeInclusive<u8>> {
+    let (start, end) = range
+        .split_once("..")
+        .ok_or_else(|| anyhow::anyhow!("Expected a range like `0..2`"))?;
+    let start = match start {
+        "" => u8::MIN,
+        start => start.parse()?,
+    };
+    let end = match end.strip_prefix('=') {
+        Some(end) => end.parse()?,
+        None if end.is_empty() => u8::MAX,
+        None => end
+            .parse::<u8>()?
+            .checked_sub(1)
+            .ok_or_else(|| anyhow::anyhow!("The range `{}` is empty", range))?,
+    };
+    Ok(start..=end)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LayoutMode {
+    Full,
+    Compact,
+    /// No input, the answer is revealed with a key and graded by the user
+    Recognition,
+}
+
+impl From<&Arguments> for LayoutMode {
+    fn from(args: &Arguments) -> Self {
+        if args.compact {
+            LayoutMode::Compact
+        } else if args.recognition {
+            LayoutMode::Recognition
+        } else {
+            LayoutMode::Full
+        }
+    }
+}
+
 /// App holds the state of the application
 struct App {
     input: String,
@@ -140,6 +623,43 @@ struct App {
     current_screen: CurrentScreen,
     popup: Option<Box<dyn Popup>>,
     config: config::AppConfig,
+    layout: LayoutMode,
+    last_substitution: Option<SubstitutionConfig>,
+    /// Whether the review screen lists all accepted answers of the current card
+    show_all_variants: bool,
+    audio: AudioPlayer,
+    /// Answer of the current card that is being synthesized by the speech command
+    #[cfg(feature = "audio")]
+    pending_speech: Option<Receiver<Result<PathBuf>>>,
+    /// Special letter that was last inserted from the popup
+    last_special_letter: Option<String>,
+    /// Answers left for the current card before it is graded wrong
+    attempts_left: usize,
+    /// Last answer submitted for the current card
+    submitted_answer: String,
+    /// Whether the answer of the current card was peeked at before answering
+    peeked: bool,
+    /// Whether the answers are shown while querying every card, toggled for the whole session
+    study_mode: bool,
+    /// Whether the readings of the queried words are shown
+    show_readings: bool,
+    /// When the current card was shown, to enforce the think time
+    card_shown: Instant,
+    /// When the answer of the current card was revealed, to animate it
+    answer_revealed: Instant,
+    /// When the last card was completed, while no card is left
+    finished: Option<Instant>,
+    early_stop: EarlyStopTracker,
+    /// Whether the last reviews were correct, oldest first, for the accuracy sparkline
+    recent_outcomes: VecDeque<bool>,
+    /// Number of new cards that can be introduced per day
+    new_limit: Option<usize>,
+    state: AppState,
+    csv_log: Option<CsvLog>,
+    timer: SessionTimer,
+    /// Whether the keyboard enhancement flags were pushed and have to be popped while the
+    /// terminal is suspended
+    enhance_keyboard: bool,
 }
 
 enum InputMode {
@@ -149,25 +669,72 @@ enum InputMode {
 
 enum CurrentScreen {
     Query,
-    Review { correct: bool },
+    Review {
+        correct: bool,
+    },
+    /// The answer is shown in recognition mode and waits for the user to grade it
+    Reveal,
 }
 
 enum KeyHandleResult {
-    Quit { save: bool },
+    Quit {
+        save: bool,
+    },
+    /// Look up the current card with the configured dictionary command
+    Lookup,
+    /// Ask for a deck and a file name to split the current file
+    Split,
     None,
 }
 
 impl App {
-    fn new(config: AppConfig, session: VocaSession) -> App {
-        App {
+    fn new(
+        config: AppConfig,
+        session: VocaSession,
+        state: AppState,
+        layout: LayoutMode,
+        new_limit: Option<usize>,
+        csv_log: Option<CsvLog>,
+        enhance_keyboard: bool,
+    ) -> App {
+        let mut app = App {
             input: String::new(),
             cursor_pos: 0,
             input_mode: InputMode::Normal,
             voca_session: session,
             current_screen: CurrentScreen::Query,
             popup: None,
+            layout,
+            last_substitution: None,
+            show_all_variants: false,
+            audio: AudioPlayer::default(),
+            #[cfg(feature = "audio")]
+            pending_speech: None,
+            last_special_letter: None,
+            attempts_left: 0,
+            submitted_answer: String::new(),
+            peeked: false,
+            study_mode: false,
+            show_readings: config.session.show_readings,
+            card_shown: Instant::now(),
+            answer_revealed: Instant::now(),
+            finished: None,
+            early_stop: EarlyStopTracker::new(&config.goals),
+            recent_outcomes: VecDeque::with_capacity(SPARKLINE_OUTCOMES),
+            new_limit,
+            state,
+            csv_log,
+            timer: SessionTimer::new(
+                Instant::now(),
+                (config.session.idle_timeout_seconds > 0)
+                    .then(|| Duration::from_secs(config.session.idle_timeout_seconds)),
+            ),
+            enhance_keyboard,
             config,
-        }
+        };
+        app.reset_card_state();
+        app.enter_auto_edit_mode();
+        app
     }
 
     fn move_cursor_left(&mut self) {
@@ -195,13 +762,17 @@ impl App {
                         .flat_map(|s| s.special.iter())
                         .cloned()
                         .collect();
-                    Some(SpecialLettersPopup { letters })
+                    Some(SpecialLettersPopup {
+                        letters,
+                        rtl: self.target_is_rtl(),
+                    })
                 }
                 c => lang_chars
                     .iter()
                     .find(|s| s.base == c.to_string())
                     .map(|s| SpecialLettersPopup {
                         letters: s.special.to_vec(),
+                        rtl: self.target_is_rtl(),
                     }),
             };
             self.popup = popup.map(|p| Box::new(p) as Box<dyn Popup>);
@@ -209,7 +780,50 @@ impl App {
             let index = self.byte_index();
             self.input.insert(index, c);
             self.move_cursor_right();
+            self.apply_substitution();
+        }
+    }
+
+    /// Replaces a configured trigger sequence directly before the cursor with its replacement.
+    fn apply_substitution(&mut self) {
+        let Some(rules) = self
+            .voca_session
+            .current_target_lang()
+            .and_then(|lang| self.config.substitutions.0.get(lang))
+        else {
+            return;
+        };
+        let end = self.byte_index();
+        let before_cursor = &self.input[..end];
+        // Prefer the longest trigger if multiple triggers match
+        let Some(rule) = rules
+            .iter()
+            .filter(|rule| !rule.trigger.is_empty() && before_cursor.ends_with(&rule.trigger))
+            .max_by_key(|rule| rule.trigger.len())
+            .cloned()
+        else {
+            return;
+        };
+        self.input
+            .replace_range(end - rule.trigger.len()..end, &rule.replacement);
+        self.cursor_pos =
+            self.cursor_pos - rule.trigger.chars().count() + rule.replacement.chars().count();
+        self.last_substitution = Some(rule);
+    }
+
+    /// Restores the literal trigger of a substitution that was applied by the last key press.
+    fn undo_substitution(&mut self, substitution: SubstitutionConfig) {
+        let end = self.byte_index();
+        if !self.input[..end].ends_with(&substitution.replacement) {
+            self.delete_char();
+            return;
         }
+        self.input.replace_range(
+            end - substitution.replacement.len()..end,
+            &substitution.trigger,
+        );
+        self.cursor_pos = self.cursor_pos - substitution.replacement.chars().count()
+            + substitution.trigger.chars().count();
     }
 
     /// Returns the byte index based on the character position.
@@ -240,6 +854,11 @@ impl App {
         self.move_cursor_left();
     }
 
+    fn insert_str(&mut self, s: &str) {
+        self.input.insert_str(self.byte_index(), s);
+        self.cursor_pos = self.clamp_cursor(self.cursor_pos + s.chars().count());
+    }
+
     fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
         new_cursor_pos.clamp(0, self.input.chars().count())
     }
@@ -253,26 +872,262 @@ impl App {
         self.reset_cursor();
     }
 
+    /// Forgets the attempts and the peek of the previous card.
+    fn reset_card_state(&mut self) {
+        self.attempts_left = self.config.validation.attempts_before_fail.max(1);
+        self.peeked = false;
+        self.card_shown = Instant::now();
+        #[cfg(feature = "audio")]
+        {
+            self.pending_speech = None;
+        }
+    }
+
+    /// Time left of the animation that reveals the answer, if it is still running.
+    fn reveal_left(&self) -> Option<Duration> {
+        let duration = Duration::from_millis(self.config.theme.reveal_duration_ms);
+        if !matches!(
+            self.current_screen,
+            CurrentScreen::Review { .. } | CurrentScreen::Reveal
+        ) || self
+            .voca_session
+            .current_task()
+            .is_none_or(|task| task.show_answer)
+        {
+            return None;
+        }
+        duration
+            .checked_sub(self.answer_revealed.elapsed())
+            .filter(|left| !left.is_zero())
+    }
+
+    /// The part of `area` that the answer block covers while it slides in from the top.
+    fn reveal_area(&self, area: Rect) -> Rect {
+        let Some(left) = self.reveal_left() else {
+            return area;
+        };
+        let duration = Duration::from_millis(self.config.theme.reveal_duration_ms);
+        let shown = 1.0 - left.as_secs_f32() / duration.as_secs_f32();
+        Rect {
+            height: (area.height as f32 * shown).ceil() as u16,
+            ..area
+        }
+    }
+
+    /// Time left before the current card accepts an answer. Memorization cards can be
+    /// answered right away.
+    fn think_time_left(&self) -> Option<Duration> {
+        let think_time = Duration::from_millis(self.config.session.think_time_ms);
+        if !matches!(self.current_screen, CurrentScreen::Query)
+            || self
+                .voca_session
+                .current_task()
+                .is_none_or(|task| task.show_answer)
+        {
+            return None;
+        }
+        think_time
+            .checked_sub(self.card_shown.elapsed())
+            .filter(|left| !left.is_zero())
+    }
+
+    /// Whether the answer of the current card was seen before answering, either by peeking or
+    /// in study mode. Memorization cards always show their answer and are never peeked at.
+    fn answer_peeked(&self) -> bool {
+        (self.peeked || self.study_mode)
+            && self
+                .voca_session
+                .current_task()
+                .is_some_and(|task| !task.show_answer)
+    }
+
+    /// Grade of a correct answer to the current card, which is only hard after peeking.
+    fn correct_grade(&self) -> Grade {
+        match self.config.session.peek_grading {
+            PeekGrading::Hard if self.answer_peeked() => Grade::Hard,
+            _ => Grade::Correct,
+        }
+    }
+
+    /// Whether the current card fails because its answer was peeked at.
+    fn peek_fails(&self) -> bool {
+        self.answer_peeked() && self.config.session.peek_grading == PeekGrading::Wrong
+    }
+
+    /// Starts editing right away if configured and there is a card to answer.
+    fn enter_auto_edit_mode(&mut self) {
+        if self.config.session.auto_edit_on_query
+            && self.voca_session.current_task().is_some()
+            && !matches!(self.layout, LayoutMode::Recognition)
+        {
+            self.input_mode = InputMode::Editing;
+        }
+    }
+
     fn next_card(&mut self, correct: bool) {
-        self.voca_session
-            .next_card(correct, &self.config.deck_config);
+        // Memorization cards are not reviews and have no outcome
+        let grade = if correct {
+            self.correct_grade()
+        } else {
+            Grade::Wrong
+        };
+        let introduced = self.voca_session.introduced();
+        let outcome = self
+            .voca_session
+            .grade_card(grade, &self.config.deck_config);
+        if self.voca_session.introduced() > introduced {
+            self.state
+                .record_new_card(chrono::Local::now().date_naive());
+        }
+        if let Some(outcome) = outcome {
+            self.state.record_review(chrono::Local::now().date_naive());
+            if self.recent_outcomes.len() == SPARKLINE_OUTCOMES {
+                self.recent_outcomes.pop_front();
+            }
+            self.recent_outcomes.push_back(grade != Grade::Wrong);
+            if let Some(stop) = self.early_stop.record(grade != Grade::Wrong)
+                && self.popup.is_none()
+            {
+                self.popup = Some(Box::new(EarlyStopPopup {
+                    stop,
+                    save_key: self.config.keybindings.save_and_quit,
+                }));
+            }
+            if let Some(csv_log) = &mut self.csv_log
+                && let Err(err) = csv_log.log_review(&outcome, &self.submitted_answer, correct)
+            {
+                log::warn!("Failed to write to the CSV log: {}", err);
+            }
+        }
         self.current_screen = CurrentScreen::Query;
+        self.show_all_variants = false;
+        self.reset_card_state();
         self.reset_input();
-        self.input_mode = if self.voca_session.current_task().is_some() {
+        self.input_mode = if self.voca_session.current_task().is_some()
+            && !matches!(self.layout, LayoutMode::Recognition)
+        {
             InputMode::Editing
         } else {
             InputMode::Normal
         };
     }
 
+    /// Reveals and grades cards in recognition mode, where nothing is typed. Returns whether
+    /// the key was handled.
+    fn handle_recognition_key(&mut self, code: KeyCode) -> bool {
+        let keybinds = &self.config.keybindings;
+        let (accept, reject, edit_mode) = (
+            keybinds.accept_anyway,
+            keybinds.reject_anyway,
+            keybinds.edit_mode,
+        );
+        match (code, &self.current_screen) {
+            (KeyCode::Enter, CurrentScreen::Query) if self.think_time_left().is_some() => {}
+            (KeyCode::Enter, CurrentScreen::Query) => {
+                self.current_screen = CurrentScreen::Reveal;
+                self.answer_revealed = Instant::now();
+                self.play_audio();
+            }
+            (KeyCode::Enter, CurrentScreen::Reveal) => self.next_card(true),
+            (KeyCode::Char(c), CurrentScreen::Reveal) if c == accept => self.next_card(true),
+            (KeyCode::Char(c), CurrentScreen::Reveal) if c == reject => self.next_card(false),
+            (KeyCode::Char(c), _) if c == edit_mode => {}
+            _ => return false,
+        }
+        true
+    }
+
+    fn play_audio(&mut self) {
+        if let Some(path) = self.voca_session.current_audio_path() {
+            self.audio.play(&path);
+        } else {
+            #[cfg(feature = "audio")]
+            self.speak_answer();
+        }
+    }
+
+    /// Starts synthesizing the answer of the current card with the speech command, if one is
+    /// set. It is played by `play_finished_speech` once it is ready.
+    #[cfg(feature = "audio")]
+    fn speak_answer(&mut self) {
+        if self.config.speech.command.is_empty() {
+            return;
+        }
+        let Some(task) = self.voca_session.current_task() else {
+            return;
+        };
+        let Some(language) = self
+            .voca_session
+            .current_target_lang_code()
+            .or(self.voca_session.current_target_lang())
+        else {
+            return;
+        };
+        self.pending_speech = Some(speech::synthesize_in_background(
+            &self.config.speech,
+            task.answer,
+            language,
+        ));
+    }
+
+    /// Plays the synthesized answer once the speech command is done.
+    #[cfg(feature = "audio")]
+    fn play_finished_speech(&mut self) {
+        let Some(receiver) = &self.pending_speech else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(anyhow::anyhow!("The speech thread panicked")),
+        };
+        self.pending_speech = None;
+        match result {
+            Ok(path) => self.audio.play(&path),
+            Err(err) => log::warn!("Failed to speak the answer: {:#}", err),
+        }
+    }
+
     fn submit_message(&mut self) {
+        if self.think_time_left().is_some() {
+            return;
+        }
         let Some(current_task) = self.voca_session.current_task() else {
             return;
         };
-        let correct = current_task.is_correct(&self.input, &self.config.validation);
+        let correct = if self.config.session.multiline_input {
+            current_task.is_correct(&collapse_whitespace(&self.input), &self.config.validation)
+        } else {
+            current_task.is_correct(&self.input, &self.config.validation)
+        };
+        if let CurrentScreen::Query = self.current_screen {
+            self.submitted_answer = self.input.clone();
+        }
+        // A wrong answer costs a life, the card only fails once all are gone
+        if !correct
+            && !current_task.show_answer
+            && self.attempts_left > 1
+            && matches!(self.current_screen, CurrentScreen::Query)
+        {
+            self.attempts_left -= 1;
+            self.reset_input();
+            return;
+        }
+        // The compact layout has no review screen for correct answers, and memorization cards
+        // already show their answer
+        let skip_review = matches!(self.layout, LayoutMode::Compact)
+            || (current_task.show_answer && !self.config.memorization.memorization_review);
         match &self.current_screen {
+            CurrentScreen::Query if correct && !self.peek_fails() && skip_review => {
+                self.next_card(true);
+                return;
+            }
             CurrentScreen::Query => {
-                self.current_screen = CurrentScreen::Review { correct };
+                self.current_screen = CurrentScreen::Review {
+                    correct: correct && !self.peek_fails(),
+                };
+                self.answer_revealed = Instant::now();
+                self.play_audio();
             }
             CurrentScreen::Review { correct: r_correct } if correct => {
                 self.next_card(*r_correct);
@@ -286,112 +1141,445 @@ impl App {
     }
 
     fn handle_key_events(&mut self, event: KeyEvent) -> KeyHandleResult {
-        let keybinds = &self.config.keybindings;
+        // In raw mode, Ctrl+C arrives as a key press instead of a signal
+        if is_ctrl_c(&event) {
+            return self.on_ctrl_c();
+        }
         match self.input_mode {
-            InputMode::Normal => match event.code {
-                KeyCode::Char(c) if c == keybinds.edit_mode => {
-                    if let CurrentScreen::Review { correct: true } = &self.current_screen {
-                        return KeyHandleResult::None;
+            InputMode::Normal => return self.handle_normal_mode_key(event.code),
+            // Normal mode keybindings stay reachable while editing by holding Alt
+            InputMode::Editing
+                if event.kind == KeyEventKind::Press
+                    && event.modifiers.contains(KeyModifiers::ALT)
+                    && matches!(event.code, KeyCode::Char(_)) =>
+            {
+                return self.handle_normal_mode_key(event.code);
+            }
+            InputMode::Editing if event.kind == KeyEventKind::Press => {
+                // Backspace right after a substitution reverts it to the typed characters
+                let last_substitution = self.last_substitution.take();
+                match event.code {
+                    KeyCode::Enter
+                        if self.config.session.multiline_input
+                            && event
+                                .modifiers
+                                .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+                    {
+                        self.insert_str("\n")
                     }
-                    self.input_mode = InputMode::Editing;
+                    KeyCode::Enter => self.submit_message(),
+                    KeyCode::Char(c) => self.on_char_input(c, event.modifiers),
+                    KeyCode::Backspace => match last_substitution {
+                        Some(substitution) => self.undo_substitution(substitution),
+                        None => self.delete_char(),
+                    },
+                    KeyCode::Left => self.move_cursor_left(),
+                    KeyCode::Right => self.move_cursor_right(),
+                    KeyCode::Esc => self.input_mode = InputMode::Normal,
+                    _ => {}
+                }
+            }
+            InputMode::Editing => {}
+        };
+        KeyHandleResult::None
+    }
+
+    fn handle_normal_mode_key(&mut self, code: KeyCode) -> KeyHandleResult {
+        if let LayoutMode::Recognition = self.layout
+            && self.handle_recognition_key(code)
+        {
+            return KeyHandleResult::None;
+        }
+        let keybinds = &self.config.keybindings;
+        match code {
+            KeyCode::Char(c) if c == keybinds.edit_mode => {
+                if let CurrentScreen::Review { correct: true } = &self.current_screen {
+                    return KeyHandleResult::None;
                 }
-                KeyCode::Char(c) if c == keybinds.force_quit => {
+                self.input_mode = InputMode::Editing;
+            }
+            KeyCode::Char(c) if c == keybinds.force_quit => {
+                return KeyHandleResult::Quit { save: false };
+            }
+            KeyCode::Char(c) if c == keybinds.save_and_quit => {
+                if !self.voca_session.has_changes() {
                     return KeyHandleResult::Quit { save: false };
                 }
-                KeyCode::Char(c) if c == keybinds.save_and_quit => {
-                    return KeyHandleResult::Quit { save: true };
+                self.popup = Some(Box::new(SaveSummaryPopup {
+                    summary: self.voca_session.change_summary(),
+                    save_key: keybinds.save_and_quit,
+                }));
+            }
+            // Enter is a fixed alias of the continue key that only confirms correct
+            // answers, so that submitting twice never keeps a wrong one by accident
+            KeyCode::Enter => {
+                if let CurrentScreen::Review { correct: true } = self.current_screen {
+                    self.next_card(true);
                 }
-                KeyCode::Enter => {
-                    if let CurrentScreen::Review { correct: true } = &self.current_screen {
-                        self.next_card(true);
-                    }
+            }
+            // Keep the graded result, whatever it was
+            KeyCode::Char(c) if c == keybinds.continue_review => {
+                if let CurrentScreen::Review { correct } = self.current_screen {
+                    self.next_card(correct);
                 }
-                KeyCode::Char(c) if c == keybinds.accept_anyway => {
-                    if let CurrentScreen::Review { correct } = &self.current_screen {
-                        if !correct {
-                            self.next_card(true);
-                        }
-                    }
+            }
+            // The "anyway" keys only apply when they would flip the graded result
+            KeyCode::Char(c) if c == keybinds.accept_anyway => {
+                if let CurrentScreen::Review { correct: false } = self.current_screen {
+                    self.next_card(true);
                 }
-                KeyCode::Char(c) if c == keybinds.reject_anyway => {
-                    if let CurrentScreen::Review { correct } = &self.current_screen {
-                        if *correct {
-                            self.next_card(false);
-                        }
-                    }
+            }
+            KeyCode::Char(c) if c == keybinds.reject_anyway => {
+                if let CurrentScreen::Review { correct: true } = self.current_screen {
+                    self.next_card(false);
                 }
-                KeyCode::Char(c)
-                    if c == keybinds.skip
-                        && matches!(self.current_screen, CurrentScreen::Query) =>
-                {
-                    self.reset_input();
-                    self.voca_session.skip_card();
+            }
+            KeyCode::Char(c)
+                if c == keybinds.show_variants
+                    && matches!(
+                        self.current_screen,
+                        CurrentScreen::Review { .. } | CurrentScreen::Reveal
+                    ) =>
+            {
+                self.show_all_variants = !self.show_all_variants;
+            }
+            KeyCode::Char(c)
+                if (c == keybinds.decrease_deck || c == keybinds.increase_deck)
+                    && matches!(self.current_screen, CurrentScreen::Query) =>
+            {
+                let delta = if c == keybinds.increase_deck { 1 } else { -1 };
+                self.voca_session
+                    .adjust_current_deck(delta, &self.config.deck_config);
+                self.reset_input();
+                self.reset_card_state();
+                self.enter_auto_edit_mode();
+            }
+            KeyCode::Char(c)
+                if c == keybinds.mark_known
+                    && matches!(self.current_screen, CurrentScreen::Query) =>
+            {
+                self.voca_session.mark_known(&self.config.deck_config);
+                self.reset_input();
+                self.reset_card_state();
+                self.enter_auto_edit_mode();
+            }
+            KeyCode::Char(c)
+                if c == keybinds.repeat_special_letter
+                    && !matches!(self.current_screen, CurrentScreen::Review { correct: true }) =>
+            {
+                if let Some(letter) = self.last_special_letter.clone() {
+                    self.insert_str(&letter);
                 }
-                KeyCode::Char(c) if c == keybinds.help => {
-                    self.popup = Some(Box::new(HelpWidget {
-                        keybinds: self.config.keybindings.clone(),
-                    }));
+            }
+            KeyCode::Char(c)
+                if c == keybinds.lookup
+                    && matches!(
+                        self.current_screen,
+                        CurrentScreen::Review { .. } | CurrentScreen::Reveal
+                    ) =>
+            {
+                return KeyHandleResult::Lookup;
+            }
+            KeyCode::Char(c)
+                if c == keybinds.split_file
+                    && matches!(self.current_screen, CurrentScreen::Query) =>
+            {
+                return KeyHandleResult::Split;
+            }
+            KeyCode::Char(c) if c == keybinds.add_card => {
+                self.popup = Some(Box::new(AddCardPopup::new(
+                    &self.voca_session,
+                    self.config.session.multiline_input,
+                )));
+            }
+            KeyCode::Char(c)
+                if c == keybinds.focus && matches!(self.current_screen, CurrentScreen::Query) =>
+            {
+                self.popup = Some(Box::new(FocusPopup {
+                    file_paths: self
+                        .voca_session
+                        .file_paths()
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect(),
+                    focus: self.voca_session.focus(),
+                }));
+            }
+            KeyCode::Char(c)
+                if c == keybinds.work_ahead && self.voca_session.start_work_ahead() > 0 =>
+            {
+                self.reset_card_state();
+                self.enter_auto_edit_mode();
+            }
+            KeyCode::Char(c)
+                if c == keybinds.peek
+                    && matches!(self.current_screen, CurrentScreen::Query)
+                    && !matches!(self.layout, LayoutMode::Recognition) =>
+            {
+                self.peeked = self
+                    .voca_session
+                    .current_task()
+                    .is_some_and(|task| !task.show_answer);
+            }
+            KeyCode::Char(c) if c == keybinds.study_mode => {
+                self.study_mode = !self.study_mode;
+            }
+            KeyCode::Char(c) if c == keybinds.toggle_readings => {
+                self.show_readings = !self.show_readings;
+            }
+            KeyCode::Char(c) if c == keybinds.play_audio => {
+                self.play_audio();
+            }
+            KeyCode::Char(c)
+                if c == keybinds.skip && matches!(self.current_screen, CurrentScreen::Query) =>
+            {
+                self.reset_input();
+                self.voca_session
+                    .skip_card(self.config.session.max_skips_per_card);
+                self.reset_card_state();
+                self.enter_auto_edit_mode();
+            }
+            KeyCode::Char(c) if c == keybinds.stats => {
+                self.popup = Some(Box::new(StatsPopup {
+                    health: self.voca_session.deck_health(),
+                    projected_reviews: self
+                        .voca_session
+                        .projected_reviews(chrono::Local::now().naive_utc(), 7),
+                }));
+            }
+            KeyCode::Char(c) if c == keybinds.history => {
+                match self.voca_session.current_history() {
+                    Ok(entries) => self.popup = Some(Box::new(HistoryPopup { entries })),
+                    Err(err) => log::warn!("Failed to load the review history: {}", err),
                 }
-                _ => {}
-            },
-            InputMode::Editing if event.kind == KeyEventKind::Press => match event.code {
-                KeyCode::Enter => self.submit_message(),
-                KeyCode::Char(c) => self.on_char_input(c, event.modifiers),
-                KeyCode::Backspace => self.delete_char(),
-                KeyCode::Left => self.move_cursor_left(),
-                KeyCode::Right => self.move_cursor_right(),
-                KeyCode::Esc => self.input_mode = InputMode::Normal,
-                _ => {}
-            },
-            InputMode::Editing => {}
-        };
+            }
+            KeyCode::Char(c) if c == keybinds.help => {
+                self.popup = Some(Box::new(HelpWidget {
+                    keybinds: self.config.keybindings.clone(),
+                }));
+            }
+            _ => {}
+        }
         KeyHandleResult::None
     }
 
+    /// Runs the dictionary command for the current card. The terminal is handed over to the
+    /// command until it exits.
+    fn run_lookup(&self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let Some(task) = self.voca_session.current_task() else {
+            return Ok(());
+        };
+        let Some((program, args)) = self.config.dictionary.command.split_first() else {
+            return Ok(());
+        };
+        let substitute = |arg: &String| arg.replace("%w", task.query).replace("%a", task.answer);
+        let status = suspend_terminal(terminal, self.enhance_keyboard, || {
+            std::process::Command::new(substitute(program))
+                .args(args.iter().map(substitute))
+                .status()
+        })?;
+        match status {
+            Ok(status) if !status.success() => {
+                log::warn!("Dictionary command exited with {}", status)
+            }
+            Ok(_) => {}
+            Err(err) => log::warn!("Failed to run dictionary command: {}", err),
+        }
+        Ok(())
+    }
+
+    /// Adds a new card to one of the loaded files. The words, the file and whether to study the
+    /// card now are read from stdin outside of the TUI.
+    fn add_card(&mut self, card: NewCard) {
+        let was_finished = self.voca_session.current_task().is_none();
+        if let Err(err) = self.voca_session.add_card(
+            card.dataset,
+            &card.word_a,
+            &card.word_b,
+            card.study,
+            &self.config.synonyms,
+        ) {
+            log::warn!("Could not add the card: {}", err);
+            return;
+        }
+        // A new card to study after all others were done
+        if was_finished && self.voca_session.current_task().is_some() {
+            self.reset_card_state();
+            self.enter_auto_edit_mode();
+        }
+    }
+
+    /// Moves the well known cards of the current card's file into a new file. The deck and the
+    /// file name are read from stdin outside of the TUI.
+    fn run_split(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        suspend_terminal(terminal, self.enhance_keyboard, || {
+            let result = (|| {
+                let min_deck = prompt("Move cards that reached deck: ")?
+                    .parse::<u8>()
+                    .context("Invalid deck")?;
+                let file_path = prompt("New file: ")?;
+                let moved = self
+                    .voca_session
+                    .split_current_dataset(min_deck, &file_path)?;
+                anyhow::Ok(format!(
+                    "Moved {} cards to '{}', it is written when saving",
+                    moved, file_path
+                ))
+            })();
+            match result {
+                Ok(message) => println!("{}", message),
+                Err(err) => println!("Could not split the file: {:#}", err),
+            }
+            print!("Press Enter to continue");
+            std::io::stdout().flush()?;
+            std::io::stdin().read_line(&mut String::new())?;
+            anyhow::Ok(())
+        })?
+    }
+
     fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
         loop {
+            #[cfg(feature = "audio")]
+            self.play_finished_speech();
             terminal.draw(|frame| self.draw(frame))?;
-            let event = event::read()?;
-            if let Some(popup) = &mut self.popup {
-                let result = popup.handle_events(event);
-                match result {
-                    PopupEventResult::Insert(s) => {
-                        self.input.insert_str(self.byte_index(), &s);
-                        self.popup = None;
-                        self.cursor_pos = self.clamp_cursor(self.cursor_pos + s.len());
+            // Wake up regularly to keep the session timer and the think time countdown up to date
+            let timeout = if self.reveal_left().is_some() {
+                REVEAL_FRAME_INTERVAL
+            } else {
+                self.think_time_left()
+                    .map_or(Duration::from_secs(1), |left| {
+                        left.min(Duration::from_millis(100))
+                    })
+            };
+            #[cfg(feature = "audio")]
+            let timeout = if self.pending_speech.is_some() {
+                timeout.min(SPEECH_POLL_INTERVAL)
+            } else {
+                timeout
+            };
+            if !event::poll(timeout)? {
+                continue;
+            }
+            let event = event::read()?;
+            if let Event::Key(_) = event {
+                self.timer.record_activity(Instant::now());
+            }
+            let ctrl_c = matches!(&event, Event::Key(key) if is_ctrl_c(key));
+            let result = if let Some(popup) = &mut self.popup {
+                match popup.handle_events(event) {
+                    PopupEventResult::Insert(s) => {
+                        self.popup = None;
+                        self.insert_str(&s);
+                        self.last_special_letter = Some(s);
+                        KeyHandleResult::None
                     }
                     PopupEventResult::Cancel => {
                         self.popup = None;
+                        KeyHandleResult::None
                     }
-                    PopupEventResult::Ignore => {}
+                    PopupEventResult::Confirm => KeyHandleResult::Quit { save: true },
+                    PopupEventResult::Discard => KeyHandleResult::Quit { save: false },
+                    PopupEventResult::AddCard(card) => {
+                        self.popup = None;
+                        self.add_card(card);
+                        KeyHandleResult::None
+                    }
+                    PopupEventResult::Focus(focus) => {
+                        self.popup = None;
+                        self.voca_session.set_focus(focus);
+                        self.reset_input();
+                        self.reset_card_state();
+                        self.enter_auto_edit_mode();
+                        KeyHandleResult::None
+                    }
+                    // Popups that do not handle Ctrl+C themselves are closed by it
+                    PopupEventResult::Ignore if ctrl_c => {
+                        self.popup = None;
+                        self.on_ctrl_c()
+                    }
+                    PopupEventResult::Ignore => KeyHandleResult::None,
                 }
-                continue;
-            }
+            } else if let Event::Key(key) = event {
+                self.handle_key_events(key)
+            } else {
+                KeyHandleResult::None
+            };
 
-            if let Event::Key(key) = event {
-                match self.handle_key_events(key) {
-                    KeyHandleResult::Quit { save } => {
-                        if save {
-                            self.voca_session.save()?;
+            match result {
+                KeyHandleResult::Quit { save } => {
+                    if save {
+                        self.voca_session.save()?;
+                        self.state.commit();
+                        if self.tracks_daily_counts() {
+                            self.state.save()?;
                         }
-                        break Ok(());
                     }
-                    KeyHandleResult::None => {}
+                    break Ok(());
                 }
+                KeyHandleResult::Lookup => self.run_lookup(&mut terminal)?,
+                KeyHandleResult::Split => self.run_split(&mut terminal)?,
+                KeyHandleResult::None => {}
+            }
+        }
+    }
+
+    fn on_ctrl_c(&mut self) -> KeyHandleResult {
+        match self.config.session.ctrl_c {
+            CtrlCBehavior::Confirm if self.voca_session.has_changes() => {
+                self.popup = Some(Box::new(QuitPopup {
+                    keybinds: self.config.keybindings.clone(),
+                }));
+                KeyHandleResult::None
             }
+            CtrlCBehavior::SaveAndQuit => KeyHandleResult::Quit { save: true },
+            CtrlCBehavior::Confirm | CtrlCBehavior::Quit => KeyHandleResult::Quit { save: false },
         }
     }
 
     fn draw(&mut self, frame: &mut Frame) {
         let Some(current_card) = self.voca_session.current_task() else {
+            let finished = *self.finished.get_or_insert_with(Instant::now);
+            let completion_style = self.config.theme.completion_style;
             frame.render_widget(
                 NoCardsLeftScreen {
+                    banner: completion_style == CompletionStyle::Banner,
                     has_changes: self.voca_session.has_changes(),
+                    completed: self.voca_session.completed(),
+                    goal_status: self.goal_status(),
+                    new_today: self.new_today_status(),
+                    cap_reached: self.daily_cap_reached(),
+                    work_ahead: self
+                        .voca_session
+                        .work_ahead_count()
+                        .filter(|&count| count > 0)
+                        .map(|count| (self.config.keybindings.work_ahead, count)),
                 },
                 frame.area(),
             );
+            if completion_style == CompletionStyle::Flash
+                && finished.elapsed() < COMPLETION_FLASH_DURATION
+            {
+                self.draw_flash(frame, Color::Green);
+            }
+            // The save and quit confirmations are opened from this screen as well
+            if let Some(popup) = &self.popup {
+                popup.draw(frame);
+            }
             return;
         };
+        self.finished = None;
+
+        match self.layout {
+            LayoutMode::Compact => {
+                self.draw_compact(frame, &current_card);
+                return;
+            }
+            LayoutMode::Recognition => {
+                self.draw_recognition(frame, &current_card);
+                return;
+            }
+            LayoutMode::Full => {}
+        }
 
         let vertical = Layout::vertical([
             Constraint::Length(1),
@@ -412,19 +1600,43 @@ impl App {
         let msg = match self.input_mode {
             InputMode::Normal => match self.current_screen {
                 CurrentScreen::Review { correct } => {
-                    if correct {
-                        vec![
-                            "Press ".into(),
-                            keybinds.reject_anyway.to_string().bold(),
-                            " to reject anyway".into(),
-                        ]
+                    let (override_key, override_msg) = if correct {
+                        (keybinds.reject_anyway, " to reject anyway")
                     } else {
-                        vec![
-                            "Press ".into(),
-                            keybinds.accept_anyway.to_string().bold(),
-                            " to accept anyway".into(),
-                        ]
-                    }
+                        (keybinds.accept_anyway, " to accept anyway")
+                    };
+                    // Where each choice would schedule the card
+                    let deck_config = &self.config.deck_config;
+                    let (continue_preview, override_preview) = match self
+                        .voca_session
+                        .preview_interval(self.correct_grade(), deck_config)
+                        .zip(
+                            self.voca_session
+                                .preview_interval(Grade::Wrong, deck_config),
+                        ) {
+                        Some((if_correct, if_wrong)) => {
+                            let (continue_interval, override_interval) = if correct {
+                                (if_correct, if_wrong)
+                            } else {
+                                (if_wrong, if_correct)
+                            };
+                            (
+                                format!(" (→ {})", format_duration(continue_interval)),
+                                format!(" (→ {})", format_duration(override_interval)),
+                            )
+                        }
+                        None => Default::default(),
+                    };
+                    vec![
+                        "Press ".into(),
+                        keybinds.continue_review.to_string().bold(),
+                        " to continue".into(),
+                        continue_preview.dark_gray(),
+                        ", ".into(),
+                        override_key.to_string().bold(),
+                        override_msg.into(),
+                        override_preview.dark_gray(),
+                    ]
                 }
                 _ => vec![
                     "Press ".into(),
@@ -432,6 +1644,18 @@ impl App {
                     " to show keybinds".into(),
                 ],
             },
+            InputMode::Editing
+                if self.attempts_left < self.config.validation.attempts_before_fail =>
+            {
+                vec![
+                    "Not quite, try again. ".red(),
+                    "Press ".into(),
+                    "Esc".bold(),
+                    " to stop editing, ".into(),
+                    "Enter".bold(),
+                    " to submit".into(),
+                ]
+            }
             InputMode::Editing => vec![
                 "Press ".into(),
                 "Esc".bold(),
@@ -443,65 +1667,80 @@ impl App {
         let text = Text::from(Line::from(msg));
         let help_message = Paragraph::new(text);
         frame.render_widget(help_message, help_area);
-        let x = self.cursor_pos as u16;
-
-        let input = Paragraph::new(simple_soft_wrap(&self.input, input_area.width as usize - 2))
+        let input_width = input_area.width as usize - 2;
+        let input = match self.highlighted_input(&current_card) {
+            Some(highlighted) => Paragraph::new(highlighted).wrap(Wrap { trim: false }),
+            None => Paragraph::new(simple_soft_wrap(&self.input, input_width)),
+        };
+        let input = input
             .style(match self.input_mode {
                 InputMode::Normal => Style::default(),
                 InputMode::Editing => Style::default().fg(Color::LightBlue),
             })
-            .block(Block::bordered().title("Input"));
+            .block(
+                Block::bordered()
+                    .title("Input")
+                    .title(self.study_indicator())
+                    .title(self.rtl_indicator())
+                    .title(self.think_countdown().centered())
+                    .title(self.lives().right_aligned()),
+            );
         frame.render_widget(input, input_area);
 
         match self.input_mode {
             InputMode::Normal => {}
             #[allow(clippy::cast_possible_truncation)]
-            InputMode::Editing => frame.set_cursor_position(Position::new(
-                input_area.x + 1 + (x % (input_area.width - 2)),
-                input_area.y + 1 + x / (input_area.width - 2),
-            )),
+            InputMode::Editing => {
+                let (column, row) =
+                    wrapped_cursor_position(&self.input, self.cursor_pos, input_width);
+                frame.set_cursor_position(Position::new(
+                    input_area.x + 1 + column as u16,
+                    input_area.y + 1 + row as u16,
+                ))
+            }
         }
 
         frame.render_widget(
-            Paragraph::new(current_card.query)
+            Paragraph::new(self.query_text(&current_card))
                 .wrap(Wrap { trim: false })
-                .block(Block::bordered()),
+                .block(
+                    Block::bordered()
+                        .title(self.overdue_title())
+                        .title(self.new_badge().right_aligned()),
+                ),
             vocab_prompt_area,
         );
-        frame.render_widget(
-            format!(
-                "{}/{}",
-                self.voca_session.current_progress() + 1,
-                self.voca_session.total_tasks()
-            ),
-            progress,
-        );
+        frame.render_widget(self.progress_status(), progress);
+        self.draw_accuracy_sparkline(frame, progress);
+        frame.render_widget(self.timer_status().centered(), progress);
+        if let Some(goal_status) = self.goal_status() {
+            frame.render_widget(Line::from(goal_status).right_aligned(), progress);
+        }
 
         if let CurrentScreen::Review { correct } = &self.current_screen {
-            let area = frame.area();
-
-            let canvas = Canvas::default()
-                .x_bounds([0.0, area.width as f64])
-                .y_bounds([0.0, area.height as f64])
-                .marker(Marker::HalfBlock)
-                .paint(|ctx| {
-                    ctx.draw(&Rectangle {
-                        x: 0.0,
-                        y: 0.0,
-                        width: area.width as f64,
-                        height: area.height as f64,
-                        color: if *correct { Color::Green } else { Color::Red },
-                    });
-                });
-            frame.render_widget(canvas, area);
+            self.draw_flash(frame, if *correct { Color::Green } else { Color::Red });
         }
 
-        if matches!(self.current_screen, CurrentScreen::Review { .. }) || current_card.show_answer {
+        if matches!(self.current_screen, CurrentScreen::Review { .. })
+            || current_card.show_answer
+            || self.answer_peeked()
+        {
+            let mut answer = if self.show_all_variants {
+                Text::from_iter(current_card.listed_variants())
+            } else {
+                Text::from(current_card.answer)
+            };
+            if let CurrentScreen::Review { correct: false } = self.current_screen
+                && let Some(hint) = current_card.hint
+            {
+                answer.push_line("");
+                answer.push_line(Line::from(hint).italic());
+            }
             frame.render_widget(
-                Paragraph::new(current_card.answer)
+                Paragraph::new(answer)
                     .wrap(Wrap { trim: false })
                     .block(Block::bordered().title("Correct Answer")),
-                correct_answer_area,
+                self.reveal_area(correct_answer_area),
             );
         } else {
             frame.render_widget(Block::bordered(), correct_answer_area);
@@ -511,25 +1750,415 @@ impl App {
             popup.draw(frame);
         }
     }
+
+    /// The reviewed input with the characters that match the closest variant in green and the
+    /// others in red, if enabled.
+    fn highlighted_input(&self, task: &VocabTask) -> Option<Text<'static>> {
+        if !self.config.theme.highlight_matches
+            || !matches!(self.current_screen, CurrentScreen::Review { .. })
+        {
+            return None;
+        }
+        let (variant, _) = task.nearest_variant(&self.input)?;
+        let matched = matched_chars(&self.input, variant);
+        let mut text = Text::default();
+        let mut line = Line::default();
+        for (c, matched) in self.input.chars().zip(matched) {
+            match c {
+                '\n' => text.push_line(std::mem::take(&mut line)),
+                _ if matched => line.push_span(c.to_string().green()),
+                _ => line.push_span(c.to_string().red()),
+            }
+        }
+        text.push_line(line);
+        Some(text)
+    }
+
+    /// Remaining attempts as hearts, empty if only one attempt is configured.
+    fn lives(&self) -> Line<'static> {
+        let attempts = self.config.validation.attempts_before_fail;
+        if attempts <= 1 || !matches!(self.current_screen, CurrentScreen::Query) {
+            return Line::default();
+        }
+        let used = attempts.saturating_sub(self.attempts_left);
+        Line::from("♥".repeat(self.attempts_left) + &"♡".repeat(used)).red()
+    }
+
+    /// Whether the current card is answered in a language that is written right to left.
+    fn target_is_rtl(&self) -> bool {
+        self.voca_session
+            .current_target_lang_code()
+            .is_some_and(|code| self.config.languages.is_rtl(code))
+    }
+
+    /// Marks the input while study mode shows the answers.
+    fn study_indicator(&self) -> Line<'static> {
+        if self.study_mode {
+            Line::from("Study".dark_gray())
+        } else {
+            Line::default()
+        }
+    }
+
+    /// Marks the input of cards that are answered right to left.
+    fn rtl_indicator(&self) -> Line<'static> {
+        if self.target_is_rtl() {
+            Line::from("RTL".dark_gray())
+        } else {
+            Line::default()
+        }
+    }
+
+    /// Countdown of the think time of the current card, empty once answers are accepted.
+    fn think_countdown(&self) -> Line<'static> {
+        match self.think_time_left() {
+            Some(left) => Line::from(format!("{:.1}s", left.as_secs_f32()).dark_gray()),
+            None => Line::default(),
+        }
+    }
+
+    /// Bracketed parts of the answer shown next to the query, if enabled.
+    fn answer_hint(&self, task: &VocabTask) -> Option<String> {
+        if !self.config.session.show_answer_hints || task.answer_hints.is_empty() {
+            return None;
+        }
+        Some(format!(" {}", task.answer_hints.join(" ")))
+    }
+
+    /// Reading of the query, if the card has one and readings are shown.
+    fn query_reading<'a>(&self, task: &VocabTask<'a>) -> Option<&'a str> {
+        task.query_reading.filter(|_| self.show_readings)
+    }
+
+    /// The query with its reading on a line above it and the answer hints after it.
+    fn query_text<'a>(&self, task: &VocabTask<'a>) -> Text<'a> {
+        let mut query = Line::from(task.query);
+        if let Some(hint) = self.answer_hint(task) {
+            query.push_span(hint.dark_gray());
+        }
+        match self.query_reading(task) {
+            Some(reading) => Text::from(vec![Line::from(reading.dark_gray()), query]),
+            None => Text::from(query),
+        }
+    }
+
+    /// Position in the session and how often the current card comes up again.
+    fn progress_status(&self) -> String {
+        let mut status = format!(
+            "{}/{}",
+            self.voca_session.current_progress() + 1,
+            self.voca_session.total_tasks()
+        );
+        let card_remaining = self
+            .voca_session
+            .remaining_for_current_card()
+            .saturating_sub(1);
+        if card_remaining > 0 {
+            status.push_str(&format!(" (+{} for this card)", card_remaining));
+        }
+        if let Some(focus) = self.voca_session.focus() {
+            status.push_str(&format!(" in {}", self.voca_session.file_paths()[focus]));
+        }
+        status
+    }
+
+    /// Draws the outcomes of the last reviews right after the progress status, as full bars
+    /// for correct and half bars for wrong answers.
+    fn draw_accuracy_sparkline(&self, frame: &mut Frame, area: Rect) {
+        if !self.config.theme.accuracy_sparkline
+            || self.recent_outcomes.len() < SPARKLINE_MIN_OUTCOMES
+        {
+            return;
+        }
+        let offset = Line::from(self.progress_status()).width() as u16 + 2;
+        let area = Rect {
+            x: area.x + offset,
+            width: (SPARKLINE_OUTCOMES as u16).min(area.width.saturating_sub(offset)),
+            height: 1,
+            ..area
+        };
+        let data: Vec<u64> = self
+            .recent_outcomes
+            .iter()
+            .map(|&correct| if correct { 2 } else { 1 })
+            .collect();
+        frame.render_widget(Sparkline::default().data(data).max(2).green(), area);
+    }
+
+    /// Active time of the session, marked as paused while idle.
+    fn timer_status(&self) -> Line<'static> {
+        let now = Instant::now();
+        let mut status = Line::from(format_elapsed(self.timer.elapsed(now)));
+        if self.timer.is_paused(now) {
+            status.push_span(" (paused)".dark_gray());
+        }
+        status
+    }
+
+    /// Progress towards today's goal and the current streak, if a daily goal is set.
+    fn goal_status(&self) -> Option<String> {
+        let daily_goal = self.config.goals.daily_goal?;
+        let today = chrono::Local::now().date_naive();
+        Some(format!(
+            "Today: {}/{} | Streak: {} days",
+            self.state.reviews_on(today),
+            daily_goal,
+            self.state.streak(today, daily_goal)
+        ))
+    }
+
+    /// Whether the daily counts are needed for a goal, cap or new card limit and thus saved.
+    fn tracks_daily_counts(&self) -> bool {
+        self.config.goals.daily_goal.is_some()
+            || self.config.goals.daily_cap.is_some()
+            || self.new_limit.is_some()
+    }
+
+    /// Number of new cards introduced today in all sessions, and the daily limit if one is set.
+    fn new_today_status(&self) -> String {
+        let new_today = self.state.new_cards_on(chrono::Local::now().date_naive());
+        match self.new_limit {
+            Some(limit) => format!("New cards introduced today: {}/{}", new_today, limit),
+            None => format!("New cards introduced today: {}", new_today),
+        }
+    }
+
+    /// Whether today's reviews of all sessions reached the daily cap.
+    fn daily_cap_reached(&self) -> bool {
+        self.config
+            .goals
+            .daily_cap
+            .is_some_and(|cap| self.state.reviews_on(chrono::Local::now().date_naive()) >= cap)
+    }
+
+    /// Marks cards that had never been answered before this session.
+    fn new_badge(&self) -> Line<'static> {
+        if self.voca_session.current_is_new() {
+            Line::from(" NEW ".black().on_yellow())
+        } else {
+            Line::default()
+        }
+    }
+
+    fn overdue_title(&self) -> Line<'static> {
+        let Some(overdue) = self.voca_session.current_overdue() else {
+            return Line::default();
+        };
+        if overdue < chrono::Duration::minutes(1) {
+            return Line::default();
+        }
+        let title = Line::from(format!("Overdue by {}", format_duration(overdue)));
+        match self.config.theme.overdue_color(overdue) {
+            Some(color) => title.fg(color),
+            None => title,
+        }
+    }
+
+    /// Draws the current card as a single `prompt -> [input]` line.
+    fn draw_compact(&self, frame: &mut Frame, current_card: &VocabTask) {
+        let [card_area, _, progress] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .margin(1)
+        .areas(frame.area());
+
+        let mut line = self.new_badge();
+        if !line.spans.is_empty() {
+            line.push_span(" ");
+        }
+        line.push_span(current_card.query.bold());
+        if let Some(reading) = self.query_reading(current_card) {
+            line.push_span(format!(" ({})", reading).dark_gray());
+        }
+        if let Some(hint) = self.answer_hint(current_card) {
+            line.push_span(hint.dark_gray());
+        }
+        line.push_span(" -> [");
+        let input_offset = line.width();
+        line.extend([self.input.as_str().fg(Color::LightBlue), "]".into()]);
+        match self.current_screen {
+            CurrentScreen::Review { correct: true } => line.push_span(" ✓".green()),
+            CurrentScreen::Review { correct: false } => {
+                line.push_span(format!(" ✗ {}", current_card.answer).red());
+                if let Some(hint) = current_card.hint {
+                    line.push_span(format!(" ({})", hint).italic());
+                }
+            }
+            CurrentScreen::Query if current_card.show_answer || self.answer_peeked() => {
+                line.push_span(format!(" ({})", current_card.answer).dark_gray())
+            }
+            CurrentScreen::Query | CurrentScreen::Reveal => {}
+        }
+        for status in [
+            self.study_indicator(),
+            self.rtl_indicator(),
+            self.lives(),
+            self.think_countdown(),
+        ] {
+            if !status.spans.is_empty() {
+                line.push_span(" ");
+                line.extend(status.spans);
+            }
+        }
+        if let InputMode::Editing = self.input_mode {
+            let before_cursor =
+                Span::raw(self.input.chars().take(self.cursor_pos).collect::<String>());
+            let offset = input_offset + before_cursor.width();
+            frame.set_cursor_position(Position::new(card_area.x + offset as u16, card_area.y));
+        }
+        frame.render_widget(Paragraph::new(line), card_area);
+
+        frame.render_widget(self.progress_status().dark_gray(), progress);
+        self.draw_accuracy_sparkline(frame, progress);
+        frame.render_widget(self.timer_status().centered().dark_gray(), progress);
+        if let Some(goal_status) = self.goal_status() {
+            frame.render_widget(
+                Line::from(goal_status).right_aligned().dark_gray(),
+                progress,
+            );
+        }
+
+        if let Some(popup) = &self.popup {
+            popup.draw(frame);
+        }
+    }
+
+    /// Fills the configured band of the screen with `color`.
+    fn draw_flash(&self, frame: &mut Frame, color: Color) {
+        let area = self.config.theme.flash_area(frame.area());
+
+        let canvas = Canvas::default()
+            .x_bounds([0.0, area.width as f64])
+            .y_bounds([0.0, area.height as f64])
+            .marker(Marker::HalfBlock)
+            .paint(|ctx| {
+                ctx.draw(&Rectangle {
+                    x: 0.0,
+                    y: 0.0,
+                    width: area.width as f64,
+                    height: area.height as f64,
+                    color,
+                });
+            });
+        frame.render_widget(canvas, area);
+    }
+
+    /// Draws the query next to the answer, which is revealed on request instead of typed.
+    fn draw_recognition(&self, frame: &mut Frame, current_card: &VocabTask) {
+        let [help_area, prompt_area, progress] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .margin(1)
+        .areas(frame.area());
+        let [query_area, answer_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(prompt_area);
+
+        let keybinds = &self.config.keybindings;
+        let help = match self.current_screen {
+            CurrentScreen::Reveal => {
+                let (correct_preview, wrong_preview) = match self
+                    .voca_session
+                    .preview_intervals(&self.config.deck_config)
+                {
+                    Some((if_correct, if_wrong)) => (
+                        format!(" (→ {})", format_duration(if_correct)),
+                        format!(" (→ {})", format_duration(if_wrong)),
+                    ),
+                    None => Default::default(),
+                };
+                Line::from(vec![
+                    "Press ".into(),
+                    keybinds.accept_anyway.to_string().bold(),
+                    " if you knew it".into(),
+                    correct_preview.dark_gray(),
+                    ", ".into(),
+                    keybinds.reject_anyway.to_string().bold(),
+                    " if you did not".into(),
+                    wrong_preview.dark_gray(),
+                ])
+            }
+            _ => Line::from(vec![
+                "Press ".into(),
+                "Enter".bold(),
+                " to show the answer, ".into(),
+                keybinds.help.to_string().bold(),
+                " to show keybinds".into(),
+            ]),
+        };
+        frame.render_widget(help, help_area);
+
+        frame.render_widget(
+            Paragraph::new(self.query_text(current_card))
+                .wrap(Wrap { trim: false })
+                .block(
+                    Block::bordered()
+                        .title(self.overdue_title())
+                        .title(self.new_badge().right_aligned()),
+                ),
+            query_area,
+        );
+        if matches!(self.current_screen, CurrentScreen::Reveal) || current_card.show_answer {
+            let answer = if self.show_all_variants {
+                Text::from_iter(current_card.listed_variants())
+            } else {
+                Text::from(current_card.answer)
+            };
+            frame.render_widget(
+                Paragraph::new(answer)
+                    .wrap(Wrap { trim: false })
+                    .block(Block::bordered().title("Answer")),
+                self.reveal_area(answer_area),
+            );
+        } else {
+            frame.render_widget(
+                Block::bordered().title(self.think_countdown().centered()),
+                answer_area,
+            );
+        }
+
+        frame.render_widget(self.progress_status(), progress);
+        self.draw_accuracy_sparkline(frame, progress);
+        frame.render_widget(self.timer_status().centered(), progress);
+        if let Some(goal_status) = self.goal_status() {
+            frame.render_widget(Line::from(goal_status).right_aligned(), progress);
+        }
+
+        if let Some(popup) = &self.popup {
+            popup.draw(frame);
+        }
+    }
 }
 
 trait Popup {
-    fn handle_events(&self, event: Event) -> PopupEventResult;
+    fn handle_events(&mut self, event: Event) -> PopupEventResult;
     fn draw(&self, frame: &mut Frame);
 }
 
 struct SpecialLettersPopup {
     letters: Vec<String>,
+    /// Lay out the letters right to left, for languages written in that direction
+    rtl: bool,
 }
 
 enum PopupEventResult {
     Insert(String),
     Cancel,
+    /// Save the session and quit
+    Confirm,
+    /// Quit without saving
+    Discard,
+    /// Only test the file with the given index, or all files
+    Focus(Option<usize>),
+    AddCard(NewCard),
     Ignore,
 }
 
 impl Popup for SpecialLettersPopup {
-    fn handle_events(&self, event: Event) -> PopupEventResult {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
         const IGNORE: PopupEventResult = PopupEventResult::Ignore;
         let Event::Key(key) = event else {
             return IGNORE;
@@ -573,21 +2202,314 @@ impl Popup for SpecialLettersPopup {
         .split(area);
 
         for (i, subarea) in subareas.iter().enumerate() {
+            // The first column is the rightmost one for right-to-left languages
+            let column = if self.rtl { num_columns - 1 - i } else { i };
             let items = self
                 .letters
                 .iter()
                 .enumerate()
-                .skip(i)
+                .skip(column)
                 .step_by(num_columns)
-                .map(|(i, s)| format!("{:x}. {}", i + 1, s));
+                .map(|(i, s)| {
+                    let item = Line::from(format!("{:x}. {}", i + 1, s));
+                    if self.rtl { item.right_aligned() } else { item }
+                });
             let list = List::new(items);
             frame.render_widget(list, *subarea);
         }
     }
 }
 
+/// Picks the file that the session is restricted to, with 0 for all files.
+struct FocusPopup {
+    file_paths: Vec<String>,
+    focus: Option<usize>,
+}
+
+impl Popup for FocusPopup {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
+        let Event::Key(key) = event else {
+            return PopupEventResult::Ignore;
+        };
+        match key.code {
+            KeyCode::Esc => PopupEventResult::Cancel,
+            KeyCode::Char('0') => PopupEventResult::Focus(None),
+            // Like the special letters, files are picked with a single hex digit
+            KeyCode::Char(ch) => match ch.to_digit((self.file_paths.len() as u32 + 1).min(16)) {
+                Some(digit) if digit > 0 => PopupEventResult::Focus(Some(digit as usize - 1)),
+                _ => PopupEventResult::Ignore,
+            },
+            _ => PopupEventResult::Ignore,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let mut items = vec![(0, "All files", self.focus.is_none())];
+        items.extend(
+            self.file_paths
+                .iter()
+                .enumerate()
+                .map(|(i, file_path)| (i + 1, file_path.as_str(), self.focus == Some(i))),
+        );
+        let lines = items
+            .into_iter()
+            .map(|(i, name, current)| {
+                let line = Line::from(format!("{:x}. {}", i, name));
+                if current { line.bold() } else { line }
+            })
+            .collect::<Vec<_>>();
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+        let [area] = Layout::horizontal([Constraint::Length(width.max(20) + 4)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+            .flex(Flex::Center)
+            .areas(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            List::new(lines).block(Block::bordered().title("Focus on")),
+            area,
+        );
+    }
+}
+
+/// Card entered in the add card popup
+struct NewCard {
+    dataset: usize,
+    word_a: String,
+    word_b: String,
+    /// Whether the card is tested in this session
+    study: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum AddCardField {
+    File,
+    WordA,
+    WordB,
+    Study,
+}
+
+/// Asks for the words of a new card and the file it is added to.
+struct AddCardPopup {
+    /// Path and languages of every loaded file, and whether it is read-only
+    files: Vec<(String, String, String, bool)>,
+    dataset: usize,
+    words: [String; 2],
+    field: AddCardField,
+    study: bool,
+    /// Whether Shift+Enter inserts a line break, like in the answer input
+    multiline: bool,
+    error: Option<String>,
+}
+
+impl AddCardPopup {
+    fn new(session: &VocaSession, multiline: bool) -> Self {
+        let files = session
+            .file_paths()
+            .into_iter()
+            .enumerate()
+            .map(|(i, file_path)| {
+                let (lang_a, lang_b) = session.languages(i);
+                (
+                    file_path.to_string(),
+                    lang_a.to_string(),
+                    lang_b.to_string(),
+                    session.is_read_only(i),
+                )
+            })
+            .collect::<Vec<_>>();
+        Self {
+            // Start with the file of the current card
+            dataset: session.current_dataset().unwrap_or(0),
+            field: AddCardField::WordA,
+            files,
+            words: Default::default(),
+            study: true,
+            multiline,
+            error: None,
+        }
+    }
+
+    /// Fields in the order that Tab moves through them. The file can only be picked if there
+    /// is more than one.
+    fn fields(&self) -> Vec<AddCardField> {
+        let mut fields = vec![
+            AddCardField::WordA,
+            AddCardField::WordB,
+            AddCardField::Study,
+        ];
+        if self.files.len() > 1 {
+            fields.insert(0, AddCardField::File);
+        }
+        fields
+    }
+
+    fn move_focus(&mut self, step: isize) {
+        let fields = self.fields();
+        let current = fields
+            .iter()
+            .position(|field| *field == self.field)
+            .unwrap_or(0);
+        self.field = fields[(current as isize + step).rem_euclid(fields.len() as isize) as usize];
+    }
+
+    fn focused_word(&mut self) -> Option<&mut String> {
+        match self.field {
+            AddCardField::WordA => Some(&mut self.words[0]),
+            AddCardField::WordB => Some(&mut self.words[1]),
+            AddCardField::File | AddCardField::Study => None,
+        }
+    }
+
+    fn confirm(&mut self) -> PopupEventResult {
+        // Vocab files have one card per line
+        let [word_a, word_b] = self.words.each_ref().map(|word| collapse_whitespace(word));
+        let (file_path, .., read_only) = &self.files[self.dataset];
+        self.error = if word_a.is_empty() || word_b.is_empty() {
+            Some("Both words are needed".to_string())
+        } else if *read_only {
+            Some(format!("'{}' is read-only", file_path))
+        } else {
+            return PopupEventResult::AddCard(NewCard {
+                dataset: self.dataset,
+                word_a,
+                word_b,
+                study: self.study,
+            });
+        };
+        PopupEventResult::Ignore
+    }
+}
+
+impl Popup for AddCardPopup {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
+        let Event::Key(key) = event else {
+            return PopupEventResult::Ignore;
+        };
+        if key.kind != KeyEventKind::Press {
+            return PopupEventResult::Ignore;
+        }
+        match (key.code, self.field) {
+            (KeyCode::Esc, _) => return PopupEventResult::Cancel,
+            (KeyCode::Enter, AddCardField::WordA | AddCardField::WordB)
+                if self.multiline
+                    && key
+                        .modifiers
+                        .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+            {
+                self.focused_word()
+                    .into_iter()
+                    .for_each(|word| word.push('\n'));
+            }
+            (KeyCode::Enter, _) => return self.confirm(),
+            (KeyCode::Tab | KeyCode::Down, _) => self.move_focus(1),
+            (KeyCode::BackTab | KeyCode::Up, _) => self.move_focus(-1),
+            (KeyCode::Left, AddCardField::File) => {
+                self.dataset = (self.dataset + self.files.len() - 1) % self.files.len();
+            }
+            (KeyCode::Right | KeyCode::Char(' '), AddCardField::File) => {
+                self.dataset = (self.dataset + 1) % self.files.len();
+            }
+            (KeyCode::Left | KeyCode::Right | KeyCode::Char(' '), AddCardField::Study) => {
+                self.study = !self.study;
+            }
+            (KeyCode::Char(c), AddCardField::WordA | AddCardField::WordB)
+                if !key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.focused_word()
+                    .into_iter()
+                    .for_each(|word| word.push(c));
+            }
+            (KeyCode::Backspace, AddCardField::WordA | AddCardField::WordB) => {
+                self.focused_word().into_iter().for_each(|word| {
+                    word.pop();
+                });
+            }
+            _ => {}
+        }
+        // Ctrl+C is not handled, so it still closes the popup
+        PopupEventResult::Ignore
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let (file_path, lang_a, lang_b, _) = &self.files[self.dataset];
+        let mut fields = vec![(AddCardField::WordA, lang_a.as_str(), self.words[0].as_str())];
+        fields.push((AddCardField::WordB, lang_b.as_str(), self.words[1].as_str()));
+        let study = if self.study { "yes" } else { "no" };
+        fields.push((AddCardField::Study, "Study now", study));
+        if self.files.len() > 1 {
+            fields.insert(0, (AddCardField::File, "File", file_path.as_str()));
+        }
+        let label_width = fields
+            .iter()
+            .map(|(_, label, _)| Line::from(*label).width())
+            .max()
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        let mut cursor = None;
+        for (field, label, value) in fields {
+            let focused = field == self.field;
+            let label = format!("{:<width$}  ", label, width = label_width);
+            let label = if focused {
+                Span::from(label).bold()
+            } else {
+                Span::from(label).dark_gray()
+            };
+            // Line breaks continue below the value, aligned with the first line
+            for (i, value_line) in value.split('\n').enumerate() {
+                let prefix = if i == 0 {
+                    label.clone()
+                } else {
+                    Span::from(" ".repeat(label_width + 2))
+                };
+                let line = Line::from(vec![prefix, Span::from(value_line.to_string())]);
+                if focused && matches!(field, AddCardField::WordA | AddCardField::WordB) {
+                    cursor = Some((line.width() as u16, lines.len() as u16));
+                }
+                lines.push(line);
+            }
+        }
+        lines.push(Line::default());
+        lines.push(match &self.error {
+            Some(error) => Line::from(error.as_str()).red(),
+            None => Line::from("Tab: next field | Enter: add | Esc: cancel").dark_gray(),
+        });
+
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::bordered()
+            .title("Add Card")
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area).inner(ratatui::layout::Margin::new(0, 1));
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(Paragraph::new(lines), inner);
+        if let Some((x, y)) = cursor {
+            frame.set_cursor_position(Position::new(
+                (inner.x + x).min(inner.right().saturating_sub(1)),
+                inner.y + y,
+            ));
+        }
+    }
+}
+
 struct NoCardsLeftScreen {
+    /// Whether a congratulation is shown above the title
+    banner: bool,
     has_changes: bool,
+    /// Passed directions of new and of reviewed cards
+    completed: (usize, usize),
+    goal_status: Option<String>,
+    new_today: String,
+    /// Whether the daily review cap ended the session
+    cap_reached: bool,
+    /// Key to work ahead and the number of cards it would load
+    work_ahead: Option<(char, usize)>,
 }
 
 impl Widget for NoCardsLeftScreen {
@@ -595,26 +2517,66 @@ impl Widget for NoCardsLeftScreen {
     where
         Self: Sized,
     {
-        let title = Text::raw("No cards left!").bold();
+        let title = Text::raw(if self.cap_reached {
+            "You've hit today's review cap!"
+        } else {
+            "No cards left!"
+        })
+        .bold();
 
-        let [title_area, _, keys_area] = Layout::vertical([
+        let [
+            banner_area,
+            title_area,
+            completed_area,
+            new_today_area,
+            goal_area,
+            _,
+            keys_area,
+        ] = Layout::vertical([
+            Constraint::Length(if self.banner { 2 } else { 0 }),
             Constraint::Length(1),
             Constraint::Length(1),
-            Constraint::Length(2),
+            Constraint::Length(1),
+            Constraint::Length(if self.goal_status.is_some() { 1 } else { 0 }),
+            Constraint::Length(1),
+            Constraint::Length(3),
         ])
         .flex(Flex::Center)
         .areas(area);
 
+        if self.banner {
+            Line::from("★ ★ ★  Well done!  ★ ★ ★")
+                .green()
+                .bold()
+                .centered()
+                .render(banner_area, buf);
+        }
+
         let [title_area] = Layout::horizontal([Constraint::Length(title.width() as u16)])
             .flex(Flex::Center)
             .areas(title_area);
         title.render(title_area, buf);
 
-        let keys = Text::raw(if self.has_changes {
+        let (new, reviews) = self.completed;
+        Line::from(format!("New cards: {} | Reviews: {}", new, reviews))
+            .centered()
+            .render(completed_area, buf);
+        Line::from(self.new_today)
+            .centered()
+            .render(new_today_area, buf);
+
+        if let Some(goal_status) = self.goal_status {
+            Line::from(goal_status).centered().render(goal_area, buf);
+        }
+
+        let mut keys = Text::raw(if self.has_changes {
             "Press 'w' to save changes and exit\nPress 'Q' to exit without saving"
         } else {
             "Press 'Q' to exit"
         });
+        if let Some((key, count)) = self.work_ahead {
+            keys.push_line(format!("Press '{}' to work ahead ({} cards)", key, count));
+        }
 
         let [keys_area] = Layout::horizontal([Constraint::Length(keys.width() as u16)])
             .flex(Flex::Center)
@@ -628,7 +2590,7 @@ struct HelpWidget {
 }
 
 impl Popup for HelpWidget {
-    fn handle_events(&self, event: Event) -> PopupEventResult {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
         let Event::Key(key) = event else {
             return PopupEventResult::Ignore;
         };
@@ -639,19 +2601,83 @@ impl Popup for HelpWidget {
     }
 
     fn draw(&self, frame: &mut Frame) {
-        let keybindings: [(&str, &str); 9] = [
+        let keybindings: [(&str, &str); 28] = [
             (&self.keybinds.force_quit.to_string(), "Quit without saving"),
             (&self.keybinds.save_and_quit.to_string(), "Save and quit"),
+            (
+                &self.keybinds.continue_review.to_string(),
+                "Continue with the graded result",
+            ),
+            ("Enter", "Continue after a correct answer"),
             (&self.keybinds.accept_anyway.to_string(), "Accept anyway"),
             (&self.keybinds.reject_anyway.to_string(), "Reject anyway"),
+            (
+                &self.keybinds.show_variants.to_string(),
+                "Toggle all accepted answers",
+            ),
+            (
+                &self.keybinds.lookup.to_string(),
+                "Look up the card in the dictionary",
+            ),
             ("Esc", "Stop editing"),
+            ("Alt+<Key>", "Use a keybinding while editing"),
             ("Ctrl+Space", "Show all special letters (in edit mode)"),
             (
                 "Ctrl+<Key>",
                 "Show special letters for <Key> (in edit mode)",
             ),
+            (
+                &self.keybinds.repeat_special_letter.to_string(),
+                "Insert the last special letter again",
+            ),
             (&self.keybinds.edit_mode.to_string(), "Enter edit mode"),
             (&self.keybinds.skip.to_string(), "Skip"),
+            (&self.keybinds.stats.to_string(), "Show statistics"),
+            (
+                &self.keybinds.history.to_string(),
+                "Show the review history of the card",
+            ),
+            (
+                &self.keybinds.play_audio.to_string(),
+                "Play the card's audio",
+            ),
+            (
+                &self.keybinds.decrease_deck.to_string(),
+                "Move the card one deck down",
+            ),
+            (
+                &self.keybinds.increase_deck.to_string(),
+                "Move the card one deck up",
+            ),
+            (
+                &self.keybinds.mark_known.to_string(),
+                "Mark the card as known",
+            ),
+            (
+                &self.keybinds.split_file.to_string(),
+                "Move known cards into a new file",
+            ),
+            (
+                &self.keybinds.focus.to_string(),
+                "Only test one of the files",
+            ),
+            (
+                &self.keybinds.work_ahead.to_string(),
+                "Work ahead once no card is left",
+            ),
+            (
+                &self.keybinds.peek.to_string(),
+                "Peek at the answer before answering",
+            ),
+            (
+                &self.keybinds.study_mode.to_string(),
+                "Show the answers of all cards while querying",
+            ),
+            (
+                &self.keybinds.toggle_readings.to_string(),
+                "Toggle the readings above the queried words",
+            ),
+            (&self.keybinds.add_card.to_string(), "Add a new card"),
         ];
         let rows = keybindings
             .iter()
@@ -693,14 +2719,412 @@ impl Popup for HelpWidget {
     }
 }
 
+/// Asks what to do with unsaved changes after Ctrl+C.
+struct QuitPopup {
+    keybinds: config::KeybindsConfig,
+}
+
+impl Popup for QuitPopup {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
+        let Event::Key(key) = event else {
+            return PopupEventResult::Ignore;
+        };
+        match key.code {
+            // Pressing Ctrl+C twice quits right away
+            _ if is_ctrl_c(&key) => PopupEventResult::Discard,
+            KeyCode::Char(c) if c == self.keybinds.save_and_quit => PopupEventResult::Confirm,
+            KeyCode::Char(c) if c == self.keybinds.force_quit => PopupEventResult::Discard,
+            KeyCode::Esc => PopupEventResult::Cancel,
+            _ => PopupEventResult::Ignore,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let text = Text::from(vec![
+            Line::from("There are unsaved changes."),
+            Line::default(),
+            Line::from(vec![
+                self.keybinds.save_and_quit.to_string().bold(),
+                ": save and quit, ".into(),
+                self.keybinds.force_quit.to_string().bold(),
+                " or Ctrl+C: quit without saving, ".into(),
+                "Esc".bold(),
+                ": back".into(),
+            ]),
+        ]);
+        let [area] = Layout::horizontal([Constraint::Length(text.width() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Length(text.height() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(text).block(
+                Block::bordered()
+                    .title("Quit?")
+                    .padding(Padding::uniform(1)),
+            ),
+            area,
+        );
+    }
+}
+
+/// Suggests ending the session because the recent accuracy crossed a threshold.
+struct EarlyStopPopup {
+    stop: EarlyStop,
+    save_key: char,
+}
+
+impl Popup for EarlyStopPopup {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
+        let Event::Key(key) = event else {
+            return PopupEventResult::Ignore;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == self.save_key => PopupEventResult::Confirm,
+            KeyCode::Esc | KeyCode::Enter => PopupEventResult::Cancel,
+            _ => PopupEventResult::Ignore,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let (title, message) = match self.stop {
+            EarlyStop::Mastered => (
+                "You're doing great!",
+                "Your recent answers were mostly correct.",
+            ),
+            EarlyStop::Tired => (
+                "Take a break?",
+                "Many recent answers were wrong, you might be tired.",
+            ),
+        };
+        let text = Text::from(vec![
+            Line::from(message),
+            Line::default(),
+            Line::from(vec![
+                self.save_key.to_string().bold(),
+                ": save and quit, ".into(),
+                "Enter".bold(),
+                " or ".into(),
+                "Esc".bold(),
+                ": keep going".into(),
+            ]),
+        ]);
+        let [area] = Layout::horizontal([Constraint::Length(text.width() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Length(text.height() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(text).block(Block::bordered().title(title).padding(Padding::uniform(1))),
+            area,
+        );
+    }
+}
+
+/// Lists what will be written before saving and quitting.
+struct SaveSummaryPopup {
+    summary: ChangeSummary,
+    save_key: char,
+}
+
+impl Popup for SaveSummaryPopup {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
+        let Event::Key(key) = event else {
+            return PopupEventResult::Ignore;
+        };
+        match key.code {
+            KeyCode::Enter | KeyCode::Char('y') => PopupEventResult::Confirm,
+            KeyCode::Char(c) if c == self.save_key => PopupEventResult::Confirm,
+            KeyCode::Esc | KeyCode::Char('n') => PopupEventResult::Cancel,
+            _ => PopupEventResult::Ignore,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let mut lines = vec![
+            Line::from(format!(
+                "{} card(s) changed deck",
+                self.summary.changed_deck
+            )),
+            Line::from(format!(
+                "{} card(s) seen for the first time",
+                self.summary.newly_seen
+            )),
+            Line::default(),
+            Line::from("Files to rewrite:").bold(),
+        ];
+        lines.extend(
+            self.summary
+                .files
+                .iter()
+                .map(|file| Line::from(format!("  {}", file))),
+        );
+        lines.push(Line::default());
+        lines.push(Line::from("Enter: save and quit, Esc: back to the session").italic());
+
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::bordered()
+                    .title("Save changes?")
+                    .padding(Padding::uniform(1)),
+            ),
+            area,
+        );
+    }
+}
+
+/// Lists the recorded reviews of the current card.
+struct HistoryPopup {
+    entries: Vec<ReviewEntry>,
+}
+
+impl Popup for HistoryPopup {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
+        let Event::Key(key) = event else {
+            return PopupEventResult::Ignore;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter => PopupEventResult::Cancel,
+            _ => PopupEventResult::Ignore,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Max(self.entries.len().max(1) as u16 + 5)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::bordered()
+            .title("Review History")
+            .padding(Padding::uniform(1));
+        frame.render_widget(Clear, area);
+        if self.entries.is_empty() {
+            frame.render_widget(Paragraph::new("No reviews recorded").block(block), area);
+            return;
+        }
+        let header = Row::new(["Reviewed", "Direction", "Deck", "Interval"]).bold();
+        let rows = self.entries.iter().map(|entry| {
+            Row::new([
+                entry.reviewed.format("%Y-%m-%d %H:%M").to_string(),
+                if entry.reverse { "reverse" } else { "normal" }.to_string(),
+                entry.deck.to_string(),
+                format_duration(entry.interval()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(16),
+                Constraint::Length(9),
+                Constraint::Length(4),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(header)
+        .block(block);
+        frame.render_widget(table, area);
+    }
+}
+
+/// Shows retention metrics of all vocab files.
+struct StatsPopup {
+    health: Vec<DeckHealth>,
+    projected_reviews: Vec<usize>,
+}
+
+impl Popup for StatsPopup {
+    fn handle_events(&mut self, event: Event) -> PopupEventResult {
+        let Event::Key(key) = event else {
+            return PopupEventResult::Ignore;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter => PopupEventResult::Cancel,
+            _ => PopupEventResult::Ignore,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let header = Row::new(["File", "Seen", "Avg. deck", "Never wrong"]).bold();
+        let rows = self.health.iter().map(|health| {
+            Row::new([
+                health.name.clone(),
+                health.seen_cards.to_string(),
+                format!("{:.1}", health.average_deck),
+                format!("{:.0}%", health.never_lapsed * 100.0),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(6),
+                Constraint::Length(10),
+                Constraint::Length(12),
+            ],
+        )
+        .header(header);
+        let projected = Line::from(format!(
+            "Reviews in the next days: {}",
+            self.projected_reviews
+                .iter()
+                .map(|count| count.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+
+        let [area] = Layout::horizontal([Constraint::Percentage(70)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Max(self.health.len() as u16 + 7)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::bordered()
+            .title("Statistics")
+            .padding(Padding::uniform(1));
+        let [table_area, _, projected_area] = Layout::vertical([
+            Constraint::Length(self.health.len() as u16 + 1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(block.inner(area));
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(table, table_area);
+        frame.render_widget(projected, projected_area);
+    }
+}
+
+/// Leaves the TUI while `f` runs, so that it can use the terminal. With `enhance_keyboard`,
+/// the keyboard enhancement flags are popped meanwhile, so that Ctrl+C and Esc reach `f` as
+/// usual.
+fn suspend_terminal<T>(
+    terminal: &mut DefaultTerminal,
+    enhance_keyboard: bool,
+    f: impl FnOnce() -> T,
+) -> Result<T> {
+    if enhance_keyboard {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
+    ratatui::restore();
+    let result = f();
+    crossterm::terminal::enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::cursor::SetCursorStyle::SteadyBar
+    )?;
+    if enhance_keyboard {
+        execute!(
+            terminal.backend_mut(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
+    terminal.clear()?;
+    Ok(result)
+}
+
+fn is_ctrl_c(key: &KeyEvent) -> bool {
+    key.kind == KeyEventKind::Press
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && key.code == KeyCode::Char('c')
+}
+
+/// Breaks every line of `input` after `width` characters.
 fn simple_soft_wrap(input: &str, width: usize) -> String {
-    let length = input.chars().count();
-    let mut input_wrapped = Vec::<char>::with_capacity(length + (length / width));
-    for (i, c) in input.chars().enumerate() {
-        if (i % width) == 0 && i > 0 {
-            input_wrapped.push('\n');
+    input
+        .split('\n')
+        .map(|line| {
+            let length = line.chars().count();
+            let mut line_wrapped = Vec::<char>::with_capacity(length + (length / width));
+            for (i, c) in line.chars().enumerate() {
+                if (i % width) == 0 && i > 0 {
+                    line_wrapped.push('\n');
+                }
+                line_wrapped.push(c);
+            }
+            line_wrapped.into_iter().collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Column and row of the cursor in the input as wrapped by [`simple_soft_wrap`].
+fn wrapped_cursor_position(input: &str, cursor_pos: usize, width: usize) -> (usize, usize) {
+    let (mut column, mut row) = (0, 0);
+    for c in input.chars().take(cursor_pos) {
+        if c == '\n' {
+            column = 0;
+            row += 1;
+            continue;
+        }
+        if column == width {
+            column = 0;
+            row += 1;
         }
-        input_wrapped.push(c);
+        column += 1;
+    }
+    if column == width {
+        (0, row + 1)
+    } else {
+        (column, row)
+    }
+}
+
+/// Replaces all runs of whitespace, including line breaks, with a single space.
+fn collapse_whitespace(input: &str) -> String {
+    input.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Formats a duration as a stopwatch, e.g. `4:07` or `1:04:07`.
+fn format_elapsed(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Formats a duration in its largest whole unit, e.g. `3d` or `5h`.
+fn format_duration(duration: chrono::Duration) -> String {
+    if duration.num_days() > 0 {
+        format!("{}d", duration.num_days())
+    } else if duration.num_hours() > 0 {
+        format!("{}h", duration.num_hours())
+    } else {
+        format!("{}m", duration.num_minutes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_input_exit_code() {
+        let json = serde_json::from_str::<toml::Value>("{").unwrap_err();
+        let err = anyhow::Error::from(json).context("Could not load the config");
+        assert_eq!(error_exit_code(&err), EXIT_INVALID_INPUT);
+        let toml = toml::de::from_str::<toml::Value>("a =").unwrap_err();
+        assert_eq!(error_exit_code(&toml.into()), EXIT_INVALID_INPUT);
+        assert_eq!(error_exit_code(&anyhow::anyhow!("Something else")), 1);
     }
-    input_wrapped.iter().collect()
 }
@@ -1,2 +1,5 @@
-mod voca_card;
+pub(crate) mod alignment;
+pub(crate) mod phonetic;
+pub(crate) mod review_history;
+pub(crate) mod voca_card;
 pub(crate) mod voca_session;
@@ -0,0 +1,67 @@
+/// Aligns `input` with `expected` by their edit distance and returns for every character of
+/// `input` whether it matches a character of `expected`. Extra and wrong characters are `false`.
+/// Letters are compared case-insensitively.
+pub fn matched_chars(input: &str, expected: &str) -> Vec<bool> {
+    let input = input.chars().collect::<Vec<_>>();
+    let expected = expected.chars().collect::<Vec<_>>();
+    let same = |a: char, b: char| a == b || a.to_lowercase().eq(b.to_lowercase());
+
+    // distances[i][j] is the edit distance between the first i input and j expected characters
+    let mut distances = vec![vec![0; expected.len() + 1]; input.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=input.len() {
+        for j in 1..=expected.len() {
+            let substitution = usize::from(!same(input[i - 1], expected[j - 1]));
+            distances[i][j] = (distances[i - 1][j - 1] + substitution)
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+        }
+    }
+
+    // Walk back from the end, preferring matches over edits
+    let mut matched = vec![false; input.len()];
+    let (mut i, mut j) = (input.len(), expected.len());
+    while i > 0 && j > 0 {
+        if same(input[i - 1], expected[j - 1]) && distances[i][j] == distances[i - 1][j - 1] {
+            matched[i - 1] = true;
+            i -= 1;
+            j -= 1;
+        } else if distances[i][j] == distances[i - 1][j - 1] + 1 {
+            i -= 1;
+            j -= 1;
+        } else if distances[i][j] == distances[i - 1][j] + 1 {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matched_input_chars() {
+        let marks = |input, expected| {
+            matched_chars(input, expected)
+                .into_iter()
+                .map(|matched| if matched { '+' } else { '-' })
+                .collect::<String>()
+        };
+        assert_eq!(marks("hola", "hola"), "++++");
+        assert_eq!(marks("Hola", "hola"), "++++");
+        // Wrong, extra and missing characters
+        assert_eq!(marks("hila", "hola"), "+-++");
+        assert_eq!(marks("holla", "hola"), "++-++");
+        assert_eq!(marks("hla", "hola"), "+++");
+        assert_eq!(marks("buenos dias", "buenos días"), "++++++++-++");
+        assert_eq!(marks("", "hola"), "");
+    }
+}
@@ -0,0 +1,64 @@
+/// Soundex codes of every word of `s`, so that words that sound alike get the same key.
+/// Only the letters A to Z are coded, `None` if a word has none of them.
+pub fn phonetic_key(s: &str) -> Option<Vec<String>> {
+    let key = s
+        .split_whitespace()
+        .map(soundex)
+        .collect::<Option<Vec<_>>>()?;
+    (!key.is_empty()).then_some(key)
+}
+
+/// American Soundex code of `word`: its first letter followed by three digits.
+fn soundex(word: &str) -> Option<String> {
+    let mut letters = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase());
+    let first = letters.next()?;
+    let mut code = first.to_string();
+    let mut last_digit = digit(first);
+    for c in letters {
+        let current = digit(c);
+        if current.is_some() && current != last_digit {
+            code.extend(current);
+            if code.len() == 4 {
+                break;
+            }
+        }
+        // H and W do not separate letters with the same code, vowels do
+        if !matches!(c, 'H' | 'W') {
+            last_digit = current;
+        }
+    }
+    Some(format!("{:0<4}", code))
+}
+
+fn digit(c: char) -> Option<char> {
+    match c {
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+        'D' | 'T' => Some('3'),
+        'L' => Some('4'),
+        'M' | 'N' => Some('5'),
+        'R' => Some('6'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soundex_codes() {
+        assert_eq!(soundex("Robert").as_deref(), Some("R163"));
+        assert_eq!(soundex("Rupert").as_deref(), Some("R163"));
+        assert_eq!(soundex("Tymczak").as_deref(), Some("T522"));
+        assert_eq!(soundex("Ashcraft").as_deref(), Some("A261"));
+        assert_eq!(soundex("Pfister").as_deref(), Some("P236"));
+        assert_eq!(soundex("Lee").as_deref(), Some("L000"));
+        assert_eq!(phonetic_key("Smith fox"), phonetic_key("Smyth focks"));
+        assert_eq!(phonetic_key("дом"), None);
+        assert_eq!(phonetic_key(""), None);
+    }
+}
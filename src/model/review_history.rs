@@ -0,0 +1,122 @@
+use std::io::{BufRead, Write};
+
+use chrono::NaiveDateTime;
+
+use super::voca_card::{DATE_FORMAT, VocaParseError};
+
+/// Outcome of a single review, stored in a `<file>.history` file next to the vocab file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewEntry {
+    pub word_a: String,
+    pub word_b: String,
+    pub reverse: bool,
+    pub reviewed: NaiveDateTime,
+    /// Deck the card was moved to by the review
+    pub deck: u8,
+    pub due_date: NaiveDateTime,
+}
+
+impl ReviewEntry {
+    /// Interval until the card is due again.
+    pub fn interval(&self) -> chrono::Duration {
+        self.due_date - self.reviewed
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let parts = line.split('\t').collect::<Vec<_>>();
+        let [word_a, word_b, direction, reviewed, deck, due_date] = parts[..] else {
+            return None;
+        };
+        Some(ReviewEntry {
+            word_a: word_a.to_string(),
+            word_b: word_b.to_string(),
+            reverse: match direction {
+                "0" => false,
+                "1" => true,
+                _ => return None,
+            },
+            reviewed: NaiveDateTime::parse_from_str(reviewed, DATE_FORMAT).ok()?,
+            deck: deck.parse().ok()?,
+            due_date: NaiveDateTime::parse_from_str(due_date, DATE_FORMAT).ok()?,
+        })
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.word_a,
+            self.word_b,
+            self.reverse as u8,
+            self.reviewed.format(DATE_FORMAT),
+            self.deck,
+            self.due_date.format(DATE_FORMAT)
+        )
+    }
+}
+
+pub fn history_path(file_path: &str) -> String {
+    format!("{}.history", file_path)
+}
+
+/// Reads all entries of the history file belonging to the vocab file at `file_path`.
+pub fn load(file_path: &str) -> Result<Vec<ReviewEntry>, VocaParseError> {
+    let history_path = history_path(file_path);
+    if !std::fs::exists(&history_path)? {
+        return Ok(Vec::new());
+    }
+    let reader = std::io::BufReader::new(std::fs::File::open(&history_path)?);
+    reader
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            ReviewEntry::from_line(&line?).ok_or_else(|| VocaParseError::InvalidFormat {
+                filename: history_path.clone(),
+                line: i + 1,
+                reason: "Invalid history entry".to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Appends `entries` to the history file belonging to the vocab file at `file_path`.
+pub fn append(file_path: &str, entries: &[ReviewEntry]) -> Result<(), std::io::Error> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_path(file_path))?;
+    for entry in entries {
+        writeln!(file, "{}", entry.to_line())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ruvola-history-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("deck.tsv").to_string_lossy().to_string();
+        let reviewed = NaiveDateTime::parse_from_str("2024-01-01 12:00:00", DATE_FORMAT).unwrap();
+        let entry = ReviewEntry {
+            word_a: "hello".to_string(),
+            word_b: "hola".to_string(),
+            reverse: true,
+            reviewed,
+            deck: 3,
+            due_date: reviewed + chrono::Duration::days(7),
+        };
+
+        append(&file_path, std::slice::from_ref(&entry)).unwrap();
+        append(&file_path, std::slice::from_ref(&entry)).unwrap();
+        let entries = load(&file_path).unwrap();
+        assert_eq!(entries, vec![entry.clone(), entry]);
+        assert_eq!(entries[0].interval(), chrono::Duration::days(7));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
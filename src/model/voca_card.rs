@@ -1,20 +1,71 @@
-use std::{error::Error, io::BufRead, sync::LazyLock};
+use std::{
+    collections::HashSet,
+    error::Error,
+    io::{BufRead, Read, Write},
+    sync::LazyLock,
+};
 
 use chrono::{DateTime, NaiveDateTime};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 
-use crate::FilterMode;
+pub const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+use crate::{
+    FilterMode,
+    config::{DeckOverride, FileEncoding, LanguagesConfig, LineEnding, SessionConfig, Synonyms},
+};
 
 #[derive(Debug, Clone)]
 pub struct Vocab {
     pub word_a: VocabWord,
     pub word_b: VocabWord,
     pub metadata: Option<VocabMetadata>,
+    /// How often the card has been skipped during a session
+    pub skip_count: u32,
+    /// Path to a pre-recorded audio file, relative to the vocab file
+    pub audio: Option<String>,
+    /// How often a scheduled direction of the card was answered wrong
+    pub lapses: u32,
+    /// Note for students shown after a wrong answer, e.g. `remember the accent!`
+    pub hint: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct VocabWord {
     pub base: String,
     pub variants: Vec<String>,
+    /// Parts that all have to be answered, e.g. the article and the noun of `der & Tisch`.
+    /// Empty for words without components.
+    pub components: Vec<String>,
+    /// Bracketed parts of the word, e.g. `(der)` of `Tisch (der)`, that are not required in answers
+    pub hints: Vec<String>,
+    /// Pronunciation shown above the word when it is queried, e.g. the furigana of a kanji
+    pub reading: Option<String>,
+}
+
+/// Separates the components of a word that all have to be answered
+const COMPONENT_SEPARATOR: char = '&';
+/// Marks a variant as a reference to a synonym group of the config
+const SYNONYM_GROUP_PREFIX: char = '@';
+
+/// Removes usage notes in square brackets, e.g. `[informal]` of `hola [informal]`. They are
+/// shown with the word but never have to be typed.
+pub fn strip_annotations(s: &str) -> String {
+    static ANNOTATION_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+        regex::Regex::new(r"\[[^\]]*\]").expect("Failed to compile annotation regex")
+    });
+
+    if !ANNOTATION_REGEX.is_match(s) {
+        return s.to_string();
+    }
+    ANNOTATION_REGEX
+        .replace_all(s, "")
+        .split(' ')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(" ,", ",")
 }
 
 impl VocabWord {
@@ -24,7 +75,29 @@ impl VocabWord {
         });
 
         let base = s.to_string();
-        let mut variants = vec![base.clone()];
+        // Annotations are only kept in `base`, which is shown and saved
+        let stripped = strip_annotations(s);
+        let s = stripped.as_str();
+        let hints = BRACKET_REGEX
+            .find_iter(s)
+            .map(|hint| hint.as_str().to_string())
+            .collect();
+        if s.contains(COMPONENT_SEPARATOR) {
+            let components = s
+                .split(COMPONENT_SEPARATOR)
+                .map(str::trim)
+                .filter(|component| !component.is_empty())
+                .map(str::to_string)
+                .collect();
+            return Self {
+                variants: vec![s.to_string()],
+                base,
+                components,
+                hints,
+                reading: None,
+            };
+        }
+        let mut variants = vec![s.to_string()];
         let comma_split = s.split(',').collect::<Vec<&str>>();
         // If we have only one part, base does not contain a comma, so don't do anything
         if comma_split.len() > 1 {
@@ -45,8 +118,39 @@ impl VocabWord {
             })
             .collect::<Vec<String>>();
         variants.extend(bracket_variants);
+        // Splitting and stripping can yield the same variant more than once
+        let mut seen = HashSet::new();
+        variants.retain(|variant| seen.insert(variant.clone()));
 
-        Self { base, variants }
+        Self {
+            base,
+            variants,
+            components: Vec::new(),
+            hints,
+            reading: None,
+        }
+    }
+
+    /// Replaces variants that reference a synonym group with the words of the group. `base`
+    /// keeps the reference, so it is saved unchanged. Returns the names of unknown groups.
+    pub fn expand_synonyms(&mut self, synonyms: &Synonyms) -> Vec<String> {
+        let mut unknown = Vec::new();
+        let mut variants = Vec::with_capacity(self.variants.len());
+        for variant in std::mem::take(&mut self.variants) {
+            let Some(name) = variant.strip_prefix(SYNONYM_GROUP_PREFIX) else {
+                variants.push(variant);
+                continue;
+            };
+            match synonyms.0.get(name) {
+                Some(words) => variants.extend(words.iter().cloned()),
+                None => {
+                    unknown.push(name.to_string());
+                    variants.push(variant);
+                }
+            }
+        }
+        self.variants = variants;
+        unknown
     }
 }
 
@@ -56,6 +160,12 @@ pub struct VocabMetadata {
     pub deck: u8,
     pub due_date_reverse: NaiveDateTime,
     pub deck_reverse: u8,
+    /// When the card was last answered in the normal direction
+    pub last_reviewed: Option<NaiveDateTime>,
+    /// When the card was last answered in the reverse direction
+    pub last_reviewed_reverse: Option<NaiveDateTime>,
+    /// When the card was first learned, stored in the `learned` attribute
+    pub learned: Option<NaiveDateTime>,
 }
 
 impl Default for VocabMetadata {
@@ -65,11 +175,44 @@ impl Default for VocabMetadata {
             deck: 0,
             due_date_reverse: DateTime::UNIX_EPOCH.naive_utc(),
             deck_reverse: 0,
+            last_reviewed: None,
+            last_reviewed_reverse: None,
+            learned: None,
         }
     }
 }
 
+impl VocabMetadata {
+    /// Probability of testing the reverse direction when only one direction is tested.
+    ///
+    /// Each direction is weighted with `1 / (deck + 1)`, so the reverse direction is picked
+    /// with probability `(deck + 1) / (deck + deck_reverse + 2)`.
+    pub fn reverse_probability(&self) -> f64 {
+        let weight = self.deck as f64 + 1.0;
+        let weight_reverse = self.deck_reverse as f64 + 1.0;
+        weight / (weight + weight_reverse)
+    }
+
+    /// The earlier of the two review timestamps, or `None` if a direction was never reviewed.
+    pub fn least_recently_reviewed(&self) -> Option<NaiveDateTime> {
+        self.last_reviewed.min(self.last_reviewed_reverse)
+    }
+}
+
 impl Vocab {
+    /// A card without skips, audio file, lapses or hint.
+    pub fn new(word_a: VocabWord, word_b: VocabWord, metadata: Option<VocabMetadata>) -> Self {
+        Vocab {
+            word_a,
+            word_b,
+            metadata,
+            skip_count: 0,
+            audio: None,
+            lapses: 0,
+            hint: None,
+        }
+    }
+
     pub fn is_due(
         &self,
         reverse: bool,
@@ -109,6 +252,28 @@ impl Vocab {
         }
     }
 
+    pub fn mark_reviewed(&mut self, date: NaiveDateTime, reverse: bool) {
+        let metadata = self.metadata.get_or_insert_default();
+        if reverse {
+            metadata.last_reviewed_reverse = Some(date);
+        } else {
+            metadata.last_reviewed = Some(date);
+        }
+    }
+
+    /// Whether both sides of the card are the same, so that both directions are the same task.
+    pub fn has_identical_words(&self) -> bool {
+        self.word_a.base.trim() == self.word_b.base.trim()
+    }
+
+    /// Whether the card has reached at least `min_deck` in both directions, or only in the
+    /// normal one if the reverse direction is never tested.
+    pub fn is_learned(&self, min_deck: u8, forward_only: bool) -> bool {
+        self.metadata.as_ref().is_some_and(|metadata| {
+            metadata.deck >= min_deck && (forward_only || metadata.deck_reverse >= min_deck)
+        })
+    }
+
     pub fn get_deck(&self, reverse: bool) -> Option<u8> {
         self.metadata.as_ref().map(|metadata| {
             if reverse {
@@ -122,41 +287,157 @@ impl Vocab {
     fn from_line(line: &str) -> Result<Vocab, VocaLineError> {
         use VocaLineError as VE;
 
-        let mut parts = line.split('\t');
+        // Aligned files pad the columns with spaces. Since it is not known whether a file is
+        // aligned, trailing spaces are removed from the columns of every file.
+        let mut parts = line
+            .split('\t')
+            .map(|part| part.trim_end_matches(' '))
+            .peekable();
         let word_a = parts.next().ok_or(VE::MissingWordA)?;
         let word_b = parts.next().ok_or(VE::MissingWordB)?;
-        let metadata = match parts.next() {
+        // Cards without metadata can still carry attributes, which always contain a '='
+        let metadata = match parts.next_if(|part| !part.contains('=')) {
+            // Files with uniform columns keep empty metadata columns for unanswered cards
+            Some("") => {
+                while parts.next_if(|part| part.is_empty()).is_some() {}
+                if parts.next_if(|part| !part.contains('=')).is_some() {
+                    return Err(VE::MissingDeck);
+                }
+                None
+            }
             Some(deck) => {
                 let deck = deck.parse::<u8>().map_err(|_| VE::InvalidDeck)?;
                 let date_str = parts.next().ok_or(VE::MissingDueDate)?;
-                let date = NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S")
+                let date = NaiveDateTime::parse_from_str(date_str, DATE_FORMAT)
                     .map_err(|_| VE::InvalidDueDate)?;
+                // Forward-only decks leave out the reverse direction or keep its columns empty
                 let deck_b = parts
-                    .next()
-                    .ok_or(VE::MissingDeck)?
-                    .parse::<u8>()
-                    .map_err(|_| VE::InvalidDeck)?;
-                let date_b = NaiveDateTime::parse_from_str(
-                    parts.next().ok_or(VE::MissingDueDate)?,
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .map_err(|_| VE::InvalidDueDate)?;
+                    .next_if(|part| !part.contains('='))
+                    .unwrap_or_default();
+                let date_b = parts
+                    .next_if(|part| !part.contains('='))
+                    .unwrap_or_default();
+                let (deck_b, date_b) = match (deck_b, date_b) {
+                    ("", "") => (0, DateTime::UNIX_EPOCH.naive_utc()),
+                    ("", _) => return Err(VE::MissingDeck),
+                    (_, "") => return Err(VE::MissingDueDate),
+                    (deck_b, date_b) => (
+                        deck_b.parse::<u8>().map_err(|_| VE::InvalidDeck)?,
+                        NaiveDateTime::parse_from_str(date_b, DATE_FORMAT)
+                            .map_err(|_| VE::InvalidDueDate)?,
+                    ),
+                };
+                // Review timestamps are optional so that older files stay readable
+                let mut last_reviewed = || {
+                    parts
+                        .next_if(|part| !part.contains('='))
+                        .filter(|part| !part.is_empty())
+                        .map(|part| NaiveDateTime::parse_from_str(part, DATE_FORMAT))
+                        .transpose()
+                        .map_err(|_| VE::InvalidReviewDate)
+                };
+                let last_reviewed_a = last_reviewed()?;
+                let last_reviewed_b = last_reviewed()?;
                 Some(VocabMetadata {
                     deck,
                     due_date: date,
                     deck_reverse: deck_b,
                     due_date_reverse: date_b,
+                    last_reviewed: last_reviewed_a,
+                    last_reviewed_reverse: last_reviewed_b,
+                    learned: None,
                 })
             }
 
             None => None,
         };
 
-        Ok(Vocab {
-            word_a: VocabWord::from_str(word_a),
-            word_b: VocabWord::from_str(word_b),
+        let mut vocab = Vocab::new(
+            VocabWord::from_str(word_a),
+            VocabWord::from_str(word_b),
             metadata,
-        })
+        );
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').ok_or(VE::InvalidAttribute)?;
+            match key {
+                "skips" => vocab.skip_count = value.parse().map_err(|_| VE::InvalidAttribute)?,
+                "audio" => vocab.audio = Some(value.to_string()),
+                "hint" => vocab.hint = Some(value.to_string()),
+                "reading_a" => vocab.word_a.reading = Some(value.to_string()),
+                "reading_b" => vocab.word_b.reading = Some(value.to_string()),
+                "lapses" => vocab.lapses = value.parse().map_err(|_| VE::InvalidAttribute)?,
+                "learned" => {
+                    let metadata = vocab.metadata.as_mut().ok_or(VE::InvalidAttribute)?;
+                    metadata.learned = Some(
+                        NaiveDateTime::parse_from_str(value, DATE_FORMAT)
+                            .map_err(|_| VE::InvalidAttribute)?,
+                    );
+                }
+                _ => return Err(VE::UnknownAttribute(key.to_string())),
+            }
+        }
+        Ok(vocab)
+    }
+
+    #[cfg(test)]
+    fn to_line(&self) -> String {
+        self.to_columns(false).join("\t")
+    }
+
+    /// Columns of the card in the file. `forward_only` leaves out the reverse direction.
+    fn to_columns(&self, forward_only: bool) -> Vec<String> {
+        let mut columns = vec![self.word_a.base.clone(), self.word_b.base.clone()];
+        if let Some(metadata) = &self.metadata {
+            let last_reviewed_reverse = metadata.last_reviewed_reverse.filter(|_| !forward_only);
+            let has_review_dates =
+                metadata.last_reviewed.is_some() || last_reviewed_reverse.is_some();
+            columns.extend([
+                metadata.deck.to_string(),
+                metadata.due_date.format(DATE_FORMAT).to_string(),
+            ]);
+            if !forward_only {
+                columns.extend([
+                    metadata.deck_reverse.to_string(),
+                    metadata.due_date_reverse.format(DATE_FORMAT).to_string(),
+                ]);
+            } else if has_review_dates {
+                // The review dates have fixed columns
+                columns.extend([String::new(), String::new()]);
+            }
+            if has_review_dates {
+                let format = |date: Option<NaiveDateTime>| {
+                    date.map(|date| date.format(DATE_FORMAT).to_string())
+                        .unwrap_or_default()
+                };
+                columns.extend([
+                    format(metadata.last_reviewed),
+                    format(last_reviewed_reverse),
+                ]);
+            }
+        }
+        // Attributes are only written when they differ from their default to keep files compact
+        if self.skip_count > 0 {
+            columns.push(format!("skips={}", self.skip_count));
+        }
+        if let Some(audio) = &self.audio {
+            columns.push(format!("audio={}", audio));
+        }
+        if let Some(hint) = &self.hint {
+            columns.push(format!("hint={}", hint));
+        }
+        if let Some(reading) = &self.word_a.reading {
+            columns.push(format!("reading_a={}", reading));
+        }
+        if let Some(reading) = &self.word_b.reading {
+            columns.push(format!("reading_b={}", reading));
+        }
+        if self.lapses > 0 {
+            columns.push(format!("lapses={}", self.lapses));
+        }
+        if let Some(learned) = self.metadata.as_ref().and_then(|metadata| metadata.learned) {
+            columns.push(format!("learned={}", learned.format(DATE_FORMAT)));
+        }
+        columns
     }
 }
 
@@ -168,6 +449,9 @@ enum VocaLineError {
     MissingDueDate,
     InvalidDueDate,
     InvalidDeck,
+    InvalidReviewDate,
+    InvalidAttribute,
+    UnknownAttribute(String),
 }
 
 impl std::fmt::Display for VocaLineError {
@@ -179,6 +463,9 @@ impl std::fmt::Display for VocaLineError {
             VocaLineError::MissingDueDate => write!(f, "Missing due date"),
             VocaLineError::InvalidDueDate => write!(f, "Invalid due date"),
             VocaLineError::InvalidDeck => write!(f, "Invalid deck"),
+            VocaLineError::InvalidReviewDate => write!(f, "Invalid review date"),
+            VocaLineError::InvalidAttribute => write!(f, "Invalid attribute"),
+            VocaLineError::UnknownAttribute(key) => write!(f, "Unknown attribute '{}'", key),
         }
     }
 }
@@ -200,6 +487,22 @@ pub struct VocaCardDataset {
     pub file_path: String,
     pub lang_a: String,
     pub lang_b: String,
+    /// BCP-47 code of `lang_a`, if the header language could be resolved
+    pub lang_code_a: Option<String>,
+    /// BCP-47 code of `lang_b`, if the header language could be resolved
+    pub lang_code_b: Option<String>,
+    /// Whether the file is gzip compressed
+    pub compressed: bool,
+    /// Settings that replace the global config for this file
+    pub overrides: DeckOverride,
+    /// Pad the columns with spaces when saving so that they line up in an editor
+    pub aligned: bool,
+    /// Write the metadata columns of every card, empty for cards that were never answered
+    pub uniform_columns: bool,
+    pub line_ending: LineEnding,
+    pub encoding: FileEncoding,
+    /// Whether the progress of the dataset is only kept for the session and never saved
+    pub read_only: bool,
 }
 
 #[derive(Debug)]
@@ -243,16 +546,119 @@ impl From<std::io::Error> for VocaParseError {
     }
 }
 
+/// Whether `path` is an `http://` or `https://` URL instead of a local file.
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
 impl VocaCardDataset {
+    pub fn new(file_path: &str, lang_a: &str, lang_b: &str) -> Self {
+        VocaCardDataset {
+            cards: Vec::new(),
+            file_path: file_path.to_string(),
+            lang_a: lang_a.to_string(),
+            lang_b: lang_b.to_string(),
+            lang_code_a: None,
+            lang_code_b: None,
+            compressed: file_path.ends_with(".gz"),
+            overrides: DeckOverride::default(),
+            aligned: false,
+            uniform_columns: false,
+            line_ending: LineEnding::default(),
+            encoding: FileEncoding::default(),
+            read_only: false,
+        }
+    }
+
+    #[cfg(test)]
     pub fn from_file(file_path: &str) -> Result<Self, VocaParseError> {
+        Self::from_file_encoded(file_path, FileEncoding::default())
+    }
+
+    /// Loads a file in the given character encoding, which is also used when saving it again.
+    pub fn from_file_encoded(
+        file_path: &str,
+        encoding: FileEncoding,
+    ) -> Result<Self, VocaParseError> {
         let file = std::fs::File::open(file_path)?;
-        let reader = std::io::BufReader::new(file);
+        Self::from_reader(file_path, file, encoding)
+    }
+
+    /// Downloads a vocab file from an `http://` or `https://` URL. The dataset is read-only,
+    /// since it cannot be saved back.
+    #[cfg(feature = "network")]
+    pub fn from_url(url: &str, encoding: FileEncoding) -> Result<Self, VocaParseError> {
+        let response = ureq::get(url).call().map_err(std::io::Error::other)?;
+        let mut dataset = Self::from_reader(url, response.into_body().into_reader(), encoding)?;
+        dataset.read_only = true;
+        Ok(dataset)
+    }
+
+    #[cfg(not(feature = "network"))]
+    pub fn from_url(url: &str, _encoding: FileEncoding) -> Result<Self, VocaParseError> {
+        Err(VocaParseError::IoError(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "Cannot load '{}', ruvola was built without the network feature",
+                url
+            ),
+        )))
+    }
+
+    /// Loads a local file or, if `file_path` is a URL, downloads it.
+    pub fn load(file_path: &str, encoding: FileEncoding) -> Result<Self, VocaParseError> {
+        if is_url(file_path) {
+            Self::from_url(file_path, encoding)
+        } else {
+            Self::from_file_encoded(file_path, encoding)
+        }
+    }
+
+    /// Loads a local file that a command rewrites, with the encoding and save settings of the
+    /// `[session]` config.
+    pub fn from_file_configured(
+        file_path: &str,
+        session: &SessionConfig,
+    ) -> Result<Self, VocaParseError> {
+        let mut dataset = Self::from_file_encoded(file_path, session.encoding)?;
+        dataset.apply_save_settings(session);
+        Ok(dataset)
+    }
+
+    /// Makes `save` align the columns and use the line ending that the `[session]` config asks
+    /// for.
+    pub fn apply_save_settings(&mut self, session: &SessionConfig) {
+        self.aligned = session.save_aligned;
+        self.uniform_columns = session.uniform_columns;
+        self.line_ending = session.line_ending;
+    }
+
+    /// Parses the content of a vocab file. `file_path` is where the dataset is saved to and is
+    /// used in error messages.
+    pub fn from_reader(
+        file_path: &str,
+        reader: impl Read + 'static,
+        encoding: FileEncoding,
+    ) -> Result<Self, VocaParseError> {
+        let mut reader = std::io::BufReader::new(reader);
+        let compressed = file_path.ends_with(".gz") || reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+        let mut reader: Box<dyn BufRead> = if compressed {
+            Box::new(std::io::BufReader::new(GzDecoder::new(reader)))
+        } else {
+            Box::new(reader)
+        };
+        if encoding.0 != encoding_rs::UTF_8 {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+            let (text, _) = encoding.0.decode_without_bom_handling(&bytes);
+            reader = Box::new(std::io::Cursor::new(text.into_owned().into_bytes()));
+        }
         let mut cards = Vec::new();
         let mut lines = reader.lines();
         let header = lines.next().ok_or(VocaParseError::EmptyFile {
             filename: file_path.into(),
         })??;
-        let mut parts = header.split('\t');
+        let mut parts = header.split('\t').map(|part| part.trim_end_matches(' '));
         let lang_a = parts
             .next()
             .ok_or(VocaParseError::InvalidFormat {
@@ -282,8 +688,161 @@ impl VocaCardDataset {
             file_path: file_path.to_string(),
             lang_a,
             lang_b,
+            lang_code_a: None,
+            lang_code_b: None,
+            compressed,
+            overrides: DeckOverride::default(),
+            aligned: false,
+            uniform_columns: false,
+            line_ending: LineEnding::default(),
+            encoding,
+            read_only: false,
         })
     }
+
+    /// Saves the dataset to its file, compressing it again if it was loaded from a gzip file.
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        // Encode first, so that the file stays untouched if that fails
+        let content = self.encode()?;
+        let mut file = std::fs::File::create(&self.file_path)?;
+        if self.compressed {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all(&content)?;
+            encoder.finish()?;
+            Ok(())
+        } else {
+            file.write_all(&content)
+        }
+    }
+
+    /// Content of the file in its encoding and with its line ending.
+    fn encode(&self) -> Result<Vec<u8>, std::io::Error> {
+        let content = self.to_text();
+        let (bytes, _, unmappable) = self.encoding.0.encode(&content);
+        if unmappable {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "'{}' contains characters that cannot be written in {}",
+                    self.file_path,
+                    self.encoding.0.name()
+                ),
+            ));
+        }
+        Ok(bytes.into_owned())
+    }
+
+    /// Text that `save` writes, before it is encoded.
+    pub fn to_text(&self) -> String {
+        let mut rows = vec![vec![self.lang_a.clone(), self.lang_b.clone()]];
+        rows.extend(
+            self.cards
+                .iter()
+                .map(|card| card.to_columns(self.overrides.forward_only)),
+        );
+        if self.uniform_columns {
+            pad_metadata_columns(&mut rows[1..]);
+        }
+        if self.aligned {
+            align_columns(&mut rows);
+        }
+        let mut content = String::new();
+        for row in rows {
+            content.push_str(&row.join("\t"));
+            content.push_str(self.line_ending.as_str());
+        }
+        content
+    }
+
+    /// Reads the text of a local vocab file as it is parsed, decompressed and decoded.
+    pub fn read_text(file_path: &str, encoding: FileEncoding) -> Result<String, std::io::Error> {
+        let bytes = std::fs::read(file_path)?;
+        let bytes = if file_path.ends_with(".gz") || bytes.starts_with(&GZIP_MAGIC) {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+            decompressed
+        } else {
+            bytes
+        };
+        let (text, _) = encoding.0.decode_without_bom_handling(&bytes);
+        Ok(text.into_owned())
+    }
+
+    /// Expands the synonym group references of all cards. Unknown groups are reported on
+    /// stderr, so this has to run before the terminal UI starts.
+    pub fn expand_synonyms(&mut self, synonyms: &Synonyms) {
+        for card in &mut self.cards {
+            for word in [&mut card.word_a, &mut card.word_b] {
+                for name in word.expand_synonyms(synonyms) {
+                    eprintln!(
+                        "Warning: Unknown synonym group '{}' in file '{}'",
+                        name, self.file_path
+                    );
+                }
+            }
+        }
+    }
+
+    /// Looks up the language codes of both columns. With `warn_unknown`, unknown languages are
+    /// reported on stderr, so this has to run before the terminal UI starts.
+    pub fn resolve_language_codes(&mut self, languages: &LanguagesConfig) {
+        self.lang_code_a = languages.resolve(&self.lang_a);
+        self.lang_code_b = languages.resolve(&self.lang_b);
+        if languages.warn_unknown {
+            for (lang, code) in [
+                (&self.lang_a, &self.lang_code_a),
+                (&self.lang_b, &self.lang_code_b),
+            ] {
+                if code.is_none() {
+                    eprintln!(
+                        "Warning: Unknown language '{}' in file '{}'",
+                        lang, self.file_path
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Inserts empty metadata columns before the attributes of each card row, so that every row has
+/// the deck and due date columns of both directions and the review dates if any row has them.
+fn pad_metadata_columns(rows: &mut [Vec<String>]) {
+    // Only attributes contain a '=', the words are always the first two columns
+    let metadata_end = |row: &[String]| {
+        (2..row.len())
+            .find(|&i| row[i].contains('='))
+            .unwrap_or(row.len())
+    };
+    let width = rows
+        .iter()
+        .map(|row| metadata_end(row))
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    for row in rows.iter_mut() {
+        let end = metadata_end(row);
+        row.splice(end..end, std::iter::repeat_n(String::new(), width - end));
+    }
+}
+
+/// Pads every column except the last one of each row to the width of its widest cell.
+fn align_columns(rows: &mut [Vec<String>]) {
+    let mut widths = Vec::<usize>::new();
+    for row in rows.iter() {
+        for (i, cell) in row.iter().take(row.len().saturating_sub(1)).enumerate() {
+            if i == widths.len() {
+                widths.push(0);
+            }
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+    for row in rows.iter_mut() {
+        let last = row.len().saturating_sub(1);
+        for (cell, width) in row[..last].iter_mut().zip(&widths) {
+            let padding = width - cell.chars().count();
+            cell.extend(std::iter::repeat_n(' ', padding));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -299,12 +858,12 @@ mod tests {
         assert_eq!(card.metadata.as_ref().unwrap().deck, 1);
         assert_eq!(
             card.metadata.as_ref().unwrap().due_date,
-            NaiveDateTime::parse_from_str("2023-10-01 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+            NaiveDateTime::parse_from_str("2023-10-01 12:00:00", DATE_FORMAT).unwrap()
         );
         assert_eq!(card.metadata.as_ref().unwrap().deck_reverse, 2);
         assert_eq!(
             card.metadata.as_ref().unwrap().due_date_reverse,
-            NaiveDateTime::parse_from_str("2024-10-01 13:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+            NaiveDateTime::parse_from_str("2024-10-01 13:00:00", DATE_FORMAT).unwrap()
         );
     }
 
@@ -324,5 +883,304 @@ mod tests {
         assert_eq!(card.word_b.base, "world (planet)");
         assert_eq!(card.word_a.variants, vec!["hello (greeting)", "hello"]);
         assert_eq!(card.word_b.variants, vec!["world (planet)", "world"]);
+        assert_eq!(card.word_b.hints, vec!["(planet)"]);
+        assert!(card.word_a.variants[1..].iter().all(|v| !v.contains('(')));
+
+        let word = VocabWord::from_str("hello (x), hello");
+        assert_eq!(
+            word.variants,
+            vec!["hello (x), hello", "hello (x)", "hello", "hello , hello"]
+        );
+    }
+
+    #[test]
+    fn parse_annotated_variants() {
+        let word = VocabWord::from_str("hola [informal]");
+        assert_eq!(word.base, "hola [informal]");
+        assert_eq!(word.variants, vec!["hola"]);
+
+        let word = VocabWord::from_str("hola [informal], buenos días [morning]");
+        assert_eq!(word.base, "hola [informal], buenos días [morning]");
+        assert_eq!(
+            word.variants,
+            vec!["hola, buenos días", "hola", "buenos días"]
+        );
+
+        let word = VocabWord::from_str("la [fem.] casa (house) [Spain, Mexico]");
+        assert_eq!(word.variants, vec!["la casa (house)", "la casa"]);
+        assert_eq!(word.hints, vec!["(house)"]);
+
+        let word = VocabWord::from_str("der & Tisch [furniture]");
+        assert_eq!(word.components, vec!["der", "Tisch"]);
+        assert_eq!(word.variants, vec!["der & Tisch"]);
+
+        let card = Vocab::from_line("hello\thola [informal]").unwrap();
+        assert_eq!(card.to_line(), "hello\thola [informal]");
+    }
+
+    #[test]
+    fn parse_card_with_components() {
+        let line = "table\tder & Tisch";
+        let card = Vocab::from_line(line).unwrap();
+        assert_eq!(card.word_b.components, vec!["der", "Tisch"]);
+        assert_eq!(card.word_b.variants, vec!["der & Tisch"]);
+        assert!(card.word_a.components.is_empty());
+        assert_eq!(card.to_line(), line);
+    }
+
+    #[test]
+    fn expand_synonyms() {
+        let synonyms = Synonyms(
+            [(
+                "go".to_string(),
+                vec!["to go".to_string(), "to walk".to_string()],
+            )]
+            .into(),
+        );
+        let line = "gehen\thike, @go, @unknown";
+        let mut card = Vocab::from_line(line).unwrap();
+        assert_eq!(
+            card.word_b.expand_synonyms(&synonyms),
+            vec!["unknown".to_string()]
+        );
+        assert_eq!(
+            card.word_b.variants,
+            vec![
+                "hike, @go, @unknown",
+                "hike",
+                "to go",
+                "to walk",
+                "@unknown"
+            ]
+        );
+        assert_eq!(card.to_line(), line);
+    }
+
+    #[test]
+    fn parse_card_attributes() {
+        let line = "hello\tworld\tskips=3";
+        let card = Vocab::from_line(line).unwrap();
+        assert!(card.metadata.is_none());
+        assert_eq!(card.skip_count, 3);
+        assert_eq!(card.to_line(), line);
+
+        let line = "hello\tworld\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00\tskips=1";
+        let card = Vocab::from_line(line).unwrap();
+        assert_eq!(card.metadata.as_ref().unwrap().deck_reverse, 2);
+        assert_eq!(card.skip_count, 1);
+        assert_eq!(card.to_line(), line);
+
+        assert!(Vocab::from_line("hello\tworld\tunknown=1").is_err());
+        assert!(Vocab::from_line("hello\tworld\tskips=many").is_err());
+
+        let line = "hello\tworld\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00\tlearned=2023-09-30 12:00:00";
+        let card = Vocab::from_line(line).unwrap();
+        assert!(card.metadata.as_ref().unwrap().learned.is_some());
+        assert_eq!(card.to_line(), line);
+        assert!(Vocab::from_line("hello\tworld\tlearned=2023-09-30 12:00:00").is_err());
+
+        let line = "hallo\thello\tskips=2\taudio=audio/hallo.ogg\tlapses=4";
+        let card = Vocab::from_line(line).unwrap();
+        assert_eq!(card.audio.as_deref(), Some("audio/hallo.ogg"));
+        assert_eq!(card.lapses, 4);
+        assert_eq!(card.to_line(), line);
+
+        let line = "café\tcoffee\thint=remember the accent!";
+        let card = Vocab::from_line(line).unwrap();
+        assert_eq!(card.hint.as_deref(), Some("remember the accent!"));
+        assert_eq!(card.to_line(), line);
+
+        let line = "食べる\tto eat\treading_a=たべる";
+        let card = Vocab::from_line(line).unwrap();
+        assert_eq!(card.word_a.reading.as_deref(), Some("たべる"));
+        assert_eq!(card.word_b.reading, None);
+        assert_eq!(card.to_line(), line);
+    }
+
+    #[test]
+    fn parse_review_dates() {
+        let line = "hello\tworld\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00\t2023-09-01 12:00:00\t\tskips=1";
+        let card = Vocab::from_line(line).unwrap();
+        let metadata = card.metadata.as_ref().unwrap();
+        assert_eq!(
+            metadata.last_reviewed,
+            Some(NaiveDateTime::parse_from_str("2023-09-01 12:00:00", DATE_FORMAT).unwrap())
+        );
+        assert_eq!(metadata.last_reviewed_reverse, None);
+        assert_eq!(metadata.least_recently_reviewed(), None);
+        assert_eq!(card.skip_count, 1);
+        assert_eq!(card.to_line(), line);
+
+        assert!(
+            Vocab::from_line(
+                "hello\tworld\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00\tyesterday"
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn forward_only_columns() {
+        let line = "hello\tworld\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00";
+        let mut card = Vocab::from_line(line).unwrap();
+        assert_eq!(
+            card.to_columns(true).join("\t"),
+            "hello\tworld\t1\t2023-10-01 12:00:00"
+        );
+        let card_forward = Vocab::from_line("hello\tworld\t1\t2023-10-01 12:00:00").unwrap();
+        let metadata = card_forward.metadata.as_ref().unwrap();
+        assert_eq!(metadata.deck, 1);
+        assert_eq!(metadata.deck_reverse, 0);
+
+        let reviewed = NaiveDateTime::parse_from_str("2023-09-01 12:00:00", DATE_FORMAT).unwrap();
+        card.mark_reviewed(reviewed, false);
+        card.mark_reviewed(reviewed, true);
+        let line = card.to_columns(true).join("\t");
+        assert_eq!(
+            line,
+            "hello\tworld\t1\t2023-10-01 12:00:00\t\t\t2023-09-01 12:00:00\t"
+        );
+        let card = Vocab::from_line(&line).unwrap();
+        assert_eq!(card.metadata.unwrap().last_reviewed, Some(reviewed));
+
+        assert!(Vocab::from_line("hello\tworld\t1\t2023-10-01 12:00:00\t2").is_err());
+    }
+
+    #[test]
+    fn reverse_probability() {
+        let metadata = VocabMetadata::default();
+        assert_eq!(metadata.reverse_probability(), 0.5);
+        let metadata = VocabMetadata {
+            deck: 3,
+            deck_reverse: 0,
+            ..Default::default()
+        };
+        assert_eq!(metadata.reverse_probability(), 0.8);
+    }
+
+    #[test]
+    fn gzip_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ruvola-gzip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("deck.tsv.gz").to_string_lossy().to_string();
+        let mut dataset = VocaCardDataset::new(&file_path, "de", "en");
+        dataset
+            .cards
+            .push(Vocab::from_line("Hallo\tHello").unwrap());
+        dataset.save().unwrap();
+        assert!(std::fs::read(&file_path).unwrap().starts_with(&GZIP_MAGIC));
+
+        let loaded = VocaCardDataset::from_file(&file_path).unwrap();
+        assert!(loaded.compressed);
+        assert_eq!(loaded.lang_b, "en");
+        assert_eq!(loaded.cards[0].word_a.base, "Hallo");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_from_reader() {
+        let url = "https://example.com/decks/french.tsv";
+        assert!(is_url(url));
+        assert!(!is_url("decks/french.tsv"));
+        let content = std::io::Cursor::new(b"fr\ten\nbonjour\thello\t1\t2025-01-01 00:00:00\n");
+        let dataset = VocaCardDataset::from_reader(url, content, FileEncoding::default()).unwrap();
+        assert_eq!(dataset.file_path, url);
+        assert_eq!(dataset.lang_a, "fr");
+        assert_eq!(dataset.cards[0].get_deck(false), Some(1));
+        assert!(!dataset.read_only);
+    }
+
+    #[test]
+    fn encoded_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ruvola-encoding-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("deck.tsv").to_string_lossy().to_string();
+        let latin1 = FileEncoding(encoding_rs::WINDOWS_1252);
+        let mut dataset = VocaCardDataset::new(&file_path, "de", "en");
+        dataset.encoding = latin1;
+        dataset.line_ending = LineEnding::Crlf;
+        dataset
+            .cards
+            .push(Vocab::from_line("Tschüss\tBye").unwrap());
+        dataset.save().unwrap();
+        assert_eq!(
+            std::fs::read(&file_path).unwrap(),
+            b"de\ten\r\nTsch\xfcss\tBye\r\n"
+        );
+
+        let loaded = VocaCardDataset::from_file_encoded(&file_path, latin1).unwrap();
+        assert_eq!(loaded.cards[0].word_a.base, "Tschüss");
+
+        // Characters that the encoding lacks fail without touching the file
+        dataset.cards.push(Vocab::from_line("Straße\t道").unwrap());
+        assert!(dataset.save().is_err());
+        assert_eq!(std::fs::read(&file_path).unwrap().len(), 20);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn uniform_columns() {
+        let mut rows = [
+            "Hallo\tHello\tskips=1",
+            "Tschüss\tBye\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00",
+            "Danke\tThanks\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00\t\t2023-09-01 12:00:00",
+        ]
+        .map(|line| Vocab::from_line(line).unwrap().to_columns(false));
+        pad_metadata_columns(&mut rows);
+        let lines = rows.map(|row| row.join("\t"));
+        assert_eq!(
+            lines,
+            [
+                "Hallo\tHello\t\t\t\t\t\t\tskips=1",
+                "Tschüss\tBye\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00\t\t",
+                "Danke\tThanks\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00\t\t2023-09-01 12:00:00",
+            ]
+        );
+
+        // Both widths are read back the same
+        let card = Vocab::from_line(&lines[0]).unwrap();
+        assert!(card.metadata.is_none());
+        assert_eq!(card.skip_count, 1);
+        assert!(
+            Vocab::from_line("Hallo\tHello\t\t\t\t")
+                .unwrap()
+                .metadata
+                .is_none()
+        );
+        let card = Vocab::from_line(&lines[1]).unwrap();
+        assert_eq!(
+            card.to_line(),
+            "Tschüss\tBye\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00"
+        );
+        assert!(Vocab::from_line("Hallo\tHello\t\t2023-10-01 12:00:00").is_err());
+    }
+
+    #[test]
+    fn aligned_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ruvola-aligned-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("deck.tsv").to_string_lossy().to_string();
+        let mut dataset = VocaCardDataset::new(&file_path, "de", "en");
+        dataset.aligned = true;
+        dataset.cards.extend([
+            Vocab::from_line("Hallo\tHello\tskips=1").unwrap(),
+            Vocab::from_line("Tschüss\tBye\t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00")
+                .unwrap(),
+        ]);
+        dataset.save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file_path).unwrap(),
+            "de     \ten\n\
+             Hallo  \tHello\tskips=1\n\
+             Tschüss\tBye  \t1\t2023-10-01 12:00:00\t2\t2024-10-01 13:00:00\n"
+        );
+
+        let loaded = VocaCardDataset::from_file(&file_path).unwrap();
+        assert_eq!(loaded.lang_a, "de");
+        for (loaded, card) in loaded.cards.iter().zip(&dataset.cards) {
+            assert_eq!(loaded.to_line(), card.to_line());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,35 +1,271 @@
-use std::collections::VecDeque;
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+};
 
-use rand::seq::SliceRandom;
+use chrono::{DateTime, NaiveDateTime};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 
 use crate::{
-    FilterMode, SortMode,
-    config::{DeckConfig, MemorizationConfig, ValidationConfig},
+    FilterMode, SessionOptions, SortMode,
+    config::{
+        AppConfig, DeckConfig, IdenticalWords, MemorizationConfig, Pairing, ProgressMode, Synonyms,
+        ValidationConfig,
+    },
 };
 
-use super::voca_card::{VocaCardDataset, VocaParseError, Vocab, VocabMetadata};
-use std::io::Write;
+use super::{
+    phonetic::phonetic_key,
+    review_history::{self, ReviewEntry},
+    voca_card::{
+        VocaCardDataset, VocaParseError, Vocab, VocabMetadata, VocabWord, is_url, strip_annotations,
+    },
+};
 
 pub struct VocabTask<'a> {
     pub query: &'a str,
+    /// Pronunciation of the query, e.g. its furigana
+    pub query_reading: Option<&'a str>,
     pub answer: &'a str,
     pub answer_variants: &'a [String],
+    /// Parts of the answer that all have to appear in the input
+    pub answer_components: &'a [String],
+    /// Bracketed parts of the answer, like a gender or part of speech
+    pub answer_hints: &'a [String],
     pub show_answer: bool,
+    /// Whether the card is tested from `word_b` to `word_a`
+    pub reverse: bool,
+    /// Language of the answer as named in the file header
+    pub target_lang: &'a str,
+    /// Only accept answers that match a variant exactly
+    pub exact_match: bool,
+    /// Letters with diacritics have to be typed, even if the error tolerance would forgive them
+    pub strict_diacritics: bool,
+    /// Note of the card shown after a wrong answer. Not used for grading
+    pub hint: Option<&'a str>,
 }
 
-impl VocabTask<'_> {
+impl<'a> VocabTask<'a> {
+    /// Task that tests `card` of `dataset` in the given direction.
+    pub fn new(dataset: &'a VocaCardDataset, card: &'a Vocab, reverse: bool) -> Self {
+        let (query, answer, target_lang) = if reverse {
+            (&card.word_b, &card.word_a, &dataset.lang_a)
+        } else {
+            (&card.word_a, &card.word_b, &dataset.lang_b)
+        };
+        VocabTask {
+            query: &query.base,
+            query_reading: query.reading.as_deref(),
+            answer: &answer.base,
+            answer_variants: &answer.variants,
+            answer_components: &answer.components,
+            answer_hints: &answer.hints,
+            show_answer: false,
+            reverse,
+            target_lang,
+            exact_match: dataset.overrides.exact_match,
+            strict_diacritics: dataset.overrides.strict_diacritics,
+            hint: card.hint.as_deref(),
+        }
+    }
+
     pub fn is_correct(&self, answer: &str, val_config: &ValidationConfig) -> bool {
-        for variant in self.answer_variants {
-            if variant.len() < val_config.tolerance_min_length {
-                if answer == variant {
-                    return true;
-                }
-            } else if edit_distance::edit_distance(variant, answer) <= val_config.error_tolerance {
-                return true;
+        let answer = normalize_input(answer, val_config);
+        let answer = answer.as_ref();
+        if !self.answer_components.is_empty() {
+            // Every component has to match a run of words of the input, in any order
+            let words = answer.split_whitespace().collect::<Vec<_>>();
+            return self.answer_components.iter().all(|component| {
+                let num_words = component.split_whitespace().count().max(1);
+                words
+                    .windows(num_words)
+                    .any(|window| self.matches(&window.join(" "), component, val_config))
+            });
+        }
+        self.answer_variants
+            .iter()
+            .any(|variant| self.matches(answer, variant, val_config))
+    }
+
+    fn matches(&self, answer: &str, expected: &str, val_config: &ValidationConfig) -> bool {
+        let articles = val_config
+            .articles
+            .get(self.target_lang)
+            .filter(|_| !self.exact_match);
+        let (answer, expected) = match articles {
+            Some(articles) => strip_articles(answer, expected, articles),
+            None => (answer, expected),
+        };
+        let ignore_leading_case = val_config
+            .ignore_leading_case
+            .iter()
+            .any(|lang| lang == self.target_lang);
+        let answer = if ignore_leading_case {
+            match_leading_case(answer, expected)
+        } else {
+            Cow::Borrowed(answer)
+        };
+        let (answer, expected) = if val_config.collapse_repeats && !self.exact_match {
+            (
+                Cow::Owned(collapse_repeats(&answer)),
+                Cow::Owned(collapse_repeats(expected)),
+            )
+        } else {
+            (answer, Cow::Borrowed(expected))
+        };
+        if (self.strict_diacritics || val_config.strict_diacritics)
+            && !types_diacritics(&answer, &expected)
+        {
+            return false;
+        }
+        let phonetic_match = !self.exact_match
+            && val_config
+                .phonetic_match
+                .iter()
+                .any(|lang| lang == self.target_lang);
+        if phonetic_match
+            && phonetic_key(&answer).is_some_and(|key| Some(key) == phonetic_key(&expected))
+        {
+            return true;
+        }
+        if self.exact_match || expected.len() < val_config.tolerance_min_length {
+            answer == expected
+        } else {
+            edit_distance::edit_distance(&expected, &answer)
+                <= val_config.error_tolerance(self.reverse)
+        }
+    }
+
+    /// The accepted variant closest to `answer` and its edit distance.
+    pub fn nearest_variant(&self, answer: &str) -> Option<(&'a str, usize)> {
+        self.answer_variants
+            .iter()
+            .map(|variant| {
+                (
+                    variant.as_str(),
+                    edit_distance::edit_distance(variant, answer),
+                )
+            })
+            .min_by_key(|(_, distance)| *distance)
+    }
+
+    /// Returns the variants listed in the answer, without the forms derived from brackets.
+    pub fn listed_variants(&self) -> Vec<&'a str> {
+        let mut variants: Vec<&str> = Vec::new();
+        // Listed with their annotations, which are not part of the accepted variants
+        for listed in self.answer.split(',').map(str::trim) {
+            if self.answer_variants.contains(&strip_annotations(listed))
+                && !variants.contains(&listed)
+            {
+                variants.push(listed);
             }
         }
-        false
+        variants
+    }
+}
+
+/// Removes the whitespace of `input` that the validation config ignores.
+fn normalize_input<'b>(input: &'b str, val_config: &ValidationConfig) -> Cow<'b, str> {
+    if val_config.collapse_spaces {
+        Cow::Owned(input.split_whitespace().collect::<Vec<_>>().join(" "))
+    } else if val_config.trim_input {
+        Cow::Borrowed(input.trim())
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+/// Removes a leading article from `text`, e.g. `der` from `der Tisch`. Articles that end in an
+/// apostrophe, like `l'`, do not need a space after them. The article alone is kept.
+fn strip_article<'b>(text: &'b str, articles: &[String]) -> Option<&'b str> {
+    articles.iter().find_map(|article| {
+        let prefix = text.get(..article.len())?;
+        if prefix.to_lowercase() != article.to_lowercase() {
+            return None;
+        }
+        let rest = &text[article.len()..];
+        let elided = article.ends_with(['\'', '’']);
+        let word = rest.trim_start();
+        (!word.is_empty() && (elided || word.len() < rest.len())).then_some(word)
+    })
+}
+
+/// Leaves out the article of `answer` and `expected` unless both have one, so that the article is
+/// optional but still checked when it is given.
+fn strip_articles<'b, 'c>(
+    answer: &'b str,
+    expected: &'c str,
+    articles: &[String],
+) -> (&'b str, &'c str) {
+    match (
+        strip_article(answer, articles),
+        strip_article(expected, articles),
+    ) {
+        (Some(_), Some(_)) => (answer, expected),
+        (stripped_answer, stripped_expected) => (
+            stripped_answer.unwrap_or(answer),
+            stripped_expected.unwrap_or(expected),
+        ),
+    }
+}
+
+/// Gives `answer` the first letter of `variant` if the two only differ in its case.
+fn match_leading_case<'b>(answer: &'b str, variant: &str) -> Cow<'b, str> {
+    let mut answer_chars = answer.chars();
+    match (answer_chars.next(), variant.chars().next()) {
+        (Some(a), Some(v)) if a != v && a.to_lowercase().eq(v.to_lowercase()) => {
+            Cow::Owned(format!("{}{}", v, answer_chars.as_str()))
+        }
+        _ => Cow::Borrowed(answer),
+    }
+}
+
+/// Whether every letter with a diacritic of `expected`, like `é` or `ñ`, appears in `answer` at
+/// least as often. Only Latin letters and combining marks count, so that other scripts are not
+/// affected. The case is ignored.
+fn types_diacritics(answer: &str, expected: &str) -> bool {
+    let is_diacritic = |c: &char| {
+        matches!(c, '\u{c0}'..='\u{24f}' | '\u{300}'..='\u{36f}')
+            && *c != '\u{d7}'
+            && *c != '\u{f7}'
+    };
+    let mut counts = HashMap::<char, isize>::new();
+    for c in expected
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(is_diacritic)
+    {
+        *counts.entry(c).or_default() += 1;
+    }
+    for c in answer
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(is_diacritic)
+    {
+        *counts.entry(c).or_default() -= 1;
+    }
+    counts.values().all(|&missing| missing <= 0)
+}
+
+/// Replaces every run of the same character with a single one.
+fn collapse_repeats(s: &str) -> String {
+    let mut collapsed = String::with_capacity(s.len());
+    for c in s.chars() {
+        if !collapsed.ends_with(c) {
+            collapsed.push(c);
+        }
     }
+    collapsed
+}
+
+pub struct DeckHealth {
+    pub name: String,
+    pub seen_cards: usize,
+    /// Average deck of both directions of the seen cards
+    pub average_deck: f64,
+    /// Fraction of seen cards that were never answered wrong
+    pub never_lapsed: f64,
 }
 
 #[derive(Debug)]
@@ -38,30 +274,218 @@ struct VocabItem {
     card: usize,
     reverse: bool,
     memorization_card: bool,
+    /// Number of times this item was skipped during the session
+    skips: usize,
+}
+
+/// Result of grading a single card.
+pub struct ReviewOutcome {
+    pub file_path: String,
+    pub query: String,
+    pub answer: String,
+    pub reverse: bool,
+    /// Deck before the review, `None` if the card was never scheduled
+    pub old_deck: Option<u8>,
+    pub new_deck: u8,
+}
+
+/// Changes made during a session, shown before saving.
+#[derive(Debug, Default)]
+pub struct ChangeSummary {
+    /// Number of cards that moved to a different deck in at least one direction
+    pub changed_deck: usize,
+    /// Number of cards that were answered for the first time
+    pub newly_seen: usize,
+    /// Files that will be rewritten
+    pub files: Vec<String>,
+}
+
+/// How well a card was answered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grade {
+    Wrong,
+    /// Answered correctly with help, the card stays in its deck
+    Hard,
+    Correct,
+}
+
+impl From<bool> for Grade {
+    fn from(correct: bool) -> Self {
+        if correct {
+            Grade::Correct
+        } else {
+            Grade::Wrong
+        }
+    }
+}
+
+/// Deck a card moves to from `current_deck` when it is graded. Wrong answers do not move it
+/// below `floor`, unless it already is.
+fn graded_deck(
+    current_deck: u8,
+    grade: Grade,
+    floor: u8,
+    filter_mode: FilterMode,
+    deck_config: &DeckConfig,
+) -> u8 {
+    let change_deck =
+        !matches!(filter_mode, FilterMode::All) || deck_config.change_deck_in_ignore_date;
+    // Cards stored beyond the last deck, e.g. after the intervals were shortened, are graded
+    // from the last deck
+    let last_deck = (deck_config.deck_intervals.len() as u8).saturating_sub(1);
+    let current_deck = current_deck.min(last_deck);
+    match grade {
+        _ if !change_deck => current_deck,
+        Grade::Correct => (current_deck + 1).min(last_deck),
+        Grade::Hard => current_deck,
+        Grade::Wrong => current_deck.saturating_sub(1).max(floor.min(current_deck)),
+    }
+}
+
+/// Lowest deck a wrong answer moves `card` to: `grace_min_deck` within the grace period after
+/// the card was learned, the first deck otherwise.
+fn wrong_answer_floor(card: &Vocab, current_date: NaiveDateTime, deck_config: &DeckConfig) -> u8 {
+    let learned = card.metadata.as_ref().and_then(|metadata| metadata.learned);
+    let in_grace_period = deck_config
+        .grace_period
+        .as_ref()
+        .zip(learned)
+        .is_some_and(|(period, learned)| current_date - learned <= period.0);
+    if in_grace_period {
+        deck_config.grace_min_deck
+    } else {
+        0
+    }
+}
+
+/// Local midnight at the end of today, in UTC like the due dates.
+fn start_of_next_day() -> NaiveDateTime {
+    let now = chrono::Local::now();
+    now.date_naive()
+        .succ_opt()
+        .and_then(|tomorrow| tomorrow.and_hms_opt(0, 0, 0))
+        .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+        .map_or(now.naive_utc() + chrono::Duration::days(1), |midnight| {
+            midnight.naive_utc()
+        })
+}
+
+/// Marks a vocab file as read-only when given as `<path>:ro`
+const READ_ONLY_SUFFIX: &str = ":ro";
+
+/// Splits the read-only suffix off a vocab file path. Returns the path and whether it was marked.
+pub fn split_read_only(file_path: &str) -> (&str, bool) {
+    match file_path.strip_suffix(READ_ONLY_SUFFIX) {
+        Some(file_path) => (file_path, true),
+        None => (file_path, false),
+    }
+}
+
+/// Whether the words or the hint of `card` contain `text`, which has to be lowercase.
+fn card_matches(card: &Vocab, text: &str) -> bool {
+    [&card.word_a.base, &card.word_b.base]
+        .into_iter()
+        .chain(&card.hint)
+        .any(|field| field.to_lowercase().contains(text))
+}
+
+/// Reorders `items` so that items with the same group are spread as evenly as possible. The
+/// items of a group keep their order, the `k`-th of `n` items is placed at `(k + 0.5) / n` of
+/// the way through. Groups that tie at a position keep the order of their first items.
+fn spread_groups<T>(items: Vec<T>, group: impl Fn(&T) -> usize) -> Vec<T> {
+    // Key and number of items of every group, in the order of their first items
+    let mut groups = Vec::<(usize, usize)>::new();
+    let mut positioned = items
+        .into_iter()
+        .map(|item| {
+            let key = group(&item);
+            let order = match groups.iter().position(|(other, _)| *other == key) {
+                Some(order) => order,
+                None => {
+                    groups.push((key, 0));
+                    groups.len() - 1
+                }
+            };
+            let k = groups[order].1;
+            groups[order].1 += 1;
+            (order, k, item)
+        })
+        .collect::<Vec<_>>();
+    positioned.sort_by(|(order_a, k_a, _), (order_b, k_b, _)| {
+        let (n_a, n_b) = (groups[*order_a].1, groups[*order_b].1);
+        // Compares (k_a + 0.5) / n_a with (k_b + 0.5) / n_b without rounding
+        ((2 * k_a + 1) * n_b)
+            .cmp(&((2 * k_b + 1) * n_a))
+            .then(order_a.cmp(order_b))
+    });
+    positioned.into_iter().map(|(_, _, item)| item).collect()
+}
+
+fn card_decks(card: &Vocab) -> Option<(u8, u8)> {
+    card.metadata
+        .as_ref()
+        .map(|metadata| (metadata.deck, metadata.deck_reverse))
 }
 
 pub struct VocaSession {
     datasets: Vec<VocaCardDataset>,
     queue: VecDeque<VocabItem>,
-    has_changes: bool,
+    /// Due items that are moved into the queue once the current batch has room for them
+    reserve: VecDeque<VocabItem>,
+    /// Number of items that are studied at once, all of them if not set
+    batch_size: Option<usize>,
+    /// Index of the only dataset that is tested, all datasets if not set
+    focus: Option<usize>,
+    /// Items of the other datasets while the session is focused on one
+    unfocused: VecDeque<VocabItem>,
+    /// Indices of the datasets that have to be written back when saving
+    modified_datasets: BTreeSet<usize>,
+    /// Decks of every card when the session started, to summarize the changes
+    initial_decks: Vec<Vec<Option<(u8, u8)>>>,
+    /// Whether reviews are appended to the history files when saving
+    record_history: bool,
+    /// Reviews of this session that are not saved yet, with the index of their dataset
+    history: Vec<(usize, ReviewEntry)>,
     total_due: usize,
+    /// Number of tasks answered so far, including repetitions
+    answered: usize,
+    /// Directions of cards that were new at the start of the session and were passed
+    completed_new: usize,
+    /// Number of cards that were seen for the first time in this session
+    introduced: usize,
+    /// Directions of cards that were seen before the session and were passed
+    completed_reviews: usize,
+    progress_mode: ProgressMode,
     filter_mode: FilterMode,
+    /// How far ahead cards can be loaded once nothing is due anymore, disabled if not set
+    work_ahead: Option<chrono::Duration>,
+    /// Number of remaining tasks below which cards are worked ahead on automatically, 0 if not
+    work_ahead_low_watermark: usize,
+    /// Number of cards that are worked ahead on at once
+    work_ahead_batch_size: usize,
+    /// Start of the session, to leave out cards that were already answered in it
+    started: NaiveDateTime,
 }
 
 impl VocaSession {
     fn new(
         datasets: Vec<VocaCardDataset>,
-        filter_mode: FilterMode,
-        sort_mode: SortMode,
-        limit: Option<usize>,
+        options: &SessionOptions,
         memorization_config: &MemorizationConfig,
     ) -> Self {
+        let filter_mode = options.filter_mode;
+        let mut rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
         let mut queue_seen = VecDeque::new();
         let mut queue_reverse = VecDeque::new();
         let mut queue_unseen = VecDeque::new();
         // let mut queue_reverse = VecDeque::new();
         let current_date = chrono::Local::now().naive_utc();
         let mut num_cards = 0;
+        // New cards of all datasets share one budget
+        let mut num_new = 0;
         let mut all_vocabs = datasets
             .iter()
             .enumerate()
@@ -73,7 +497,21 @@ impl VocaSession {
                     .map(move |(j, card)| ((i, j), card))
             })
             .collect::<Vec<_>>();
-        match sort_mode {
+        if let Some(text) = &options.grep {
+            all_vocabs.retain(|(_, card)| card_matches(card, text));
+        }
+        if let Some(subset_size) = options.random_subset {
+            let mut indices = rand::seq::index::sample(
+                &mut rng,
+                all_vocabs.len(),
+                subset_size.min(all_vocabs.len()),
+            )
+            .into_vec();
+            // Keep the original order so that the sort mode still applies to the subset
+            indices.sort_unstable();
+            all_vocabs = indices.into_iter().map(|i| all_vocabs[i]).collect();
+        }
+        match options.sort_mode {
             SortMode::DueDate => {
                 all_vocabs.sort_by(
                     |(_, Vocab { metadata: a, .. }), (_, Vocab { metadata: b, .. })| {
@@ -91,32 +529,84 @@ impl VocaSession {
                     },
                 );
             }
+            SortMode::LastSeen => {
+                // Unseen cards have no review timestamps and come first
+                all_vocabs.sort_by_key(|(_, card)| {
+                    card.metadata
+                        .as_ref()
+                        .and_then(VocabMetadata::least_recently_reviewed)
+                });
+            }
             SortMode::Random => {
-                let mut rng = rand::rng();
                 all_vocabs.shuffle(&mut rng);
             }
             SortMode::Original => {
                 // No sorting, keep original order
             }
         }
+        if options.spread_related {
+            all_vocabs = spread_groups(all_vocabs, |((i, _), _)| *i);
+        }
         for ((i, j), card) in all_vocabs {
-            if let Some(limit) = limit {
-                if num_cards >= limit {
-                    break;
-                }
+            let forward_only = datasets[i].overrides.forward_only;
+            if let Some(limit) = options.limit
+                && num_cards >= limit
+            {
+                break;
+            }
+            let num_reviews = queue_seen.len() + queue_reverse.len();
+            if options.review_cap.is_some_and(|cap| num_reviews >= cap) {
+                break;
             }
 
-            let add_to_queue = card.is_due(false, filter_mode, current_date);
-            let add_to_queue_reverse = card.is_due(true, filter_mode, current_date);
+            // A random subset is studied regardless of the due dates
+            let is_due = |reverse| {
+                let in_deck_range = options.deck_range.as_ref().is_none_or(|range| {
+                    // Unseen cards start in the first deck
+                    range.contains(&card.get_deck(reverse).unwrap_or(0))
+                });
+                in_deck_range
+                    && (options.random_subset.is_some()
+                        || card.is_due(reverse, filter_mode, current_date))
+            };
+            let mut add_to_queue = is_due(false);
+            let mut add_to_queue_reverse = !forward_only && is_due(true);
+            // Only test one direction, favoring the one in the lower deck
+            if options.weighted_direction
+                && add_to_queue
+                && add_to_queue_reverse
+                && let Some(metadata) = &card.metadata
+            {
+                let reverse = rng.random_bool(metadata.reverse_probability());
+                add_to_queue = !reverse;
+                add_to_queue_reverse = reverse;
+            }
+            // The last review before the cap only tests one direction
+            if add_to_queue
+                && add_to_queue_reverse
+                && options.review_cap.is_some_and(|cap| num_reviews + 2 > cap)
+            {
+                add_to_queue_reverse = false;
+            }
 
             let card_used = add_to_queue || add_to_queue_reverse;
+            let is_new = card.metadata.is_none();
+            if card_used && is_new {
+                if options.new_limit.is_some_and(|limit| num_new >= limit) {
+                    continue;
+                }
+                num_new += 1;
+            }
 
-            if card.metadata.is_none() && memorization_config.do_memorization_round && card_used {
+            // Memorizing a card gives it metadata, so cards that were memorized in a saved
+            // session are only tested when resuming and the round continues with the others
+            if is_new && memorization_config.do_memorization_round && card_used {
                 queue_unseen.push_back(VocabItem {
                     dataset: i,
                     card: j,
-                    reverse: memorization_config.memorization_reversed,
+                    reverse: memorization_config.memorization_reversed && !forward_only,
                     memorization_card: true,
+                    skips: 0,
                 });
             }
 
@@ -126,6 +616,7 @@ impl VocaSession {
                     card: j,
                     reverse: false,
                     memorization_card: false,
+                    skips: 0,
                 });
             }
 
@@ -135,6 +626,7 @@ impl VocaSession {
                     card: j,
                     reverse: true,
                     memorization_card: false,
+                    skips: 0,
                 });
             }
             if card_used {
@@ -142,6 +634,9 @@ impl VocaSession {
             }
         }
 
+        if queue_unseen.len() < memorization_config.memorization_min_new_cards {
+            queue_unseen.clear();
+        }
         for item in queue_seen {
             queue_unseen.push_back(item);
         }
@@ -149,46 +644,123 @@ impl VocaSession {
             queue_unseen.push_back(item);
         }
         let total_due = queue_unseen.len();
+        let initial_decks = datasets
+            .iter()
+            .map(|dataset| dataset.cards.iter().map(card_decks).collect())
+            .collect();
         VocaSession {
             datasets,
             queue: queue_unseen,
-            has_changes: false,
+            reserve: VecDeque::new(),
+            batch_size: None,
+            focus: None,
+            unfocused: VecDeque::new(),
+            modified_datasets: BTreeSet::new(),
+            initial_decks,
+            record_history: false,
+            history: Vec::new(),
             total_due,
+            answered: 0,
+            completed_new: 0,
+            introduced: 0,
+            completed_reviews: 0,
+            progress_mode: ProgressMode::default(),
             filter_mode,
+            work_ahead: options.work_ahead,
+            work_ahead_low_watermark: 0,
+            work_ahead_batch_size: 0,
+            started: current_date,
         }
     }
 
     #[inline(always)]
     pub fn has_changes(&self) -> bool {
-        self.has_changes
+        !self.modified_datasets.is_empty()
     }
 
-    pub fn current_task(&self) -> Option<VocabTask> {
-        self.queue.front().and_then(|index| {
-            self.datasets
-                .get(index.dataset)
-                .and_then(|d| d.cards.get(index.card))
-                .map(|card| {
-                    let query = if index.reverse {
-                        &card.word_b
-                    } else {
-                        &card.word_a
-                    };
-                    let answer = if index.reverse {
-                        &card.word_a
-                    } else {
-                        &card.word_b
-                    };
-                    VocabTask {
-                        query: &query.base,
-                        answer: &answer.base,
-                        answer_variants: &answer.variants,
-                        show_answer: index.memorization_card,
+    /// Remembers that `dataset` has to be saved. Read-only datasets are changed for the rest of
+    /// the session, so that answered cards are not asked again, but never saved.
+    fn mark_modified(&mut self, dataset: usize) {
+        if !self.datasets[dataset].read_only {
+            self.modified_datasets.insert(dataset);
+        }
+    }
+
+    /// Summarizes what `save` would write.
+    pub fn change_summary(&self) -> ChangeSummary {
+        let mut summary = ChangeSummary::default();
+        for &i in &self.modified_datasets {
+            let dataset = &self.datasets[i];
+            for (card, initial) in dataset.cards.iter().zip(&self.initial_decks[i]) {
+                match (initial, card_decks(card)) {
+                    (None, Some(_)) => summary.newly_seen += 1,
+                    (Some(initial), Some(current)) if *initial != current => {
+                        summary.changed_deck += 1
                     }
-                })
+                    _ => {}
+                }
+            }
+            summary.files.push(dataset.file_path.clone());
+        }
+        summary
+    }
+
+    pub fn current_task(&self) -> Option<VocabTask<'_>> {
+        let index = self.queue.front()?;
+        let dataset = self.datasets.get(index.dataset)?;
+        let card = dataset.cards.get(index.card)?;
+        Some(VocabTask {
+            show_answer: index.memorization_card,
+            ..VocabTask::new(dataset, card, index.reverse)
         })
     }
 
+    /// Due date of the tested direction of the current card, if it has been scheduled before.
+    pub fn current_due_date(&self) -> Option<NaiveDateTime> {
+        let index = self
+            .queue
+            .front()
+            .filter(|index| !index.memorization_card)?;
+        let metadata = self.datasets[index.dataset].cards[index.card]
+            .metadata
+            .as_ref()?;
+        let due_date = if index.reverse {
+            metadata.due_date_reverse
+        } else {
+            metadata.due_date
+        };
+        // Cards that only finished the memorization round have not been scheduled yet
+        (due_date > DateTime::UNIX_EPOCH.naive_utc()).then_some(due_date)
+    }
+
+    /// Path of the audio file attached to the current card, resolved relative to its vocab file.
+    pub fn current_audio_path(&self) -> Option<PathBuf> {
+        let index = self.queue.front()?;
+        let dataset = &self.datasets[index.dataset];
+        let audio = dataset.cards[index.card].audio.as_ref()?;
+        let vocab_dir = Path::new(&dataset.file_path)
+            .parent()
+            .unwrap_or(Path::new(""));
+        Some(vocab_dir.join(audio))
+    }
+
+    /// How long the tested direction of the current card has been due. Negative if it is not due yet.
+    pub fn current_overdue(&self) -> Option<chrono::Duration> {
+        self.current_due_date()
+            .map(|due_date| chrono::Local::now().naive_utc() - due_date)
+    }
+
+    /// BCP-47 code of the language the current card is answered in, if it could be resolved.
+    pub fn current_target_lang_code(&self) -> Option<&str> {
+        let index = self.queue.front()?;
+        let dataset = &self.datasets[index.dataset];
+        if index.reverse {
+            dataset.lang_code_a.as_deref()
+        } else {
+            dataset.lang_code_b.as_deref()
+        }
+    }
+
     pub fn current_target_lang(&self) -> Option<&str> {
         self.queue.front().and_then(|index| {
             self.datasets.get(index.dataset).map(|d| {
@@ -201,209 +773,920 @@ impl VocaSession {
         })
     }
 
-    pub fn skip_card(&mut self) {
-        if let Some(index) = self.queue.pop_front() {
+    pub fn skip_card(&mut self, max_skips: Option<usize>) {
+        let Some(mut index) = self.queue.pop_front() else {
+            return;
+        };
+        self.mark_modified(index.dataset);
+        let card = &mut self.datasets[index.dataset].cards[index.card];
+        if index.memorization_card {
             // In memorization mode, remove the card from the queue
-            if !index.memorization_card {
-                self.queue.push_back(index);
+            card.metadata = Some(VocabMetadata::default());
+        } else {
+            card.skip_count += 1;
+            index.skips += 1;
+            if max_skips.is_some_and(|max| index.skips >= max) {
+                // Bury the card for the rest of the day. Unseen cards have no due date and are
+                // only removed from the session.
+                if let Some(deck) = card.get_deck(index.reverse) {
+                    card.update_metadata(deck, start_of_next_day(), index.reverse);
+                }
             } else {
-                self.datasets[index.dataset].cards[index.card].metadata =
-                    Some(VocabMetadata::default());
-                self.has_changes = true;
+                self.queue.push_back(index);
             }
         }
+        self.refill_queue();
     }
 
-    pub fn next_card(&mut self, answer_correct: bool, deck_config: &DeckConfig) {
-        let current_date = chrono::Local::now().naive_utc();
-
-        let Some(current_item) = self.queue.pop_front() else {
+    /// Moves the tested direction of the current card `delta` decks up or down without
+    /// answering it. The card is rescheduled from its new deck and leaves the session.
+    pub fn adjust_current_deck(&mut self, delta: i8, deck_config: &DeckConfig) {
+        let Some(index) = self.queue.front() else {
             return;
         };
+        if index.memorization_card {
+            return;
+        }
+        let card = &mut self.datasets[index.dataset].cards[index.card];
+        let current_deck = card.get_deck(index.reverse).unwrap_or(0);
+        let new_deck = (current_deck as i16 + delta as i16)
+            .clamp(0, deck_config.deck_intervals.len() as i16 - 1) as u8;
+        card.update_metadata(
+            new_deck,
+            chrono::Local::now().naive_utc() + deck_config.interval(new_deck),
+            index.reverse,
+        );
+        self.mark_modified(index.dataset);
+        self.queue.pop_front();
+        self.refill_queue();
+    }
+
+    #[cfg(test)]
+    pub fn next_card(
+        &mut self,
+        answer_correct: bool,
+        deck_config: &DeckConfig,
+    ) -> Option<ReviewOutcome> {
+        self.grade_card(answer_correct.into(), deck_config)
+    }
+
+    /// Grades the current card and moves on to the next one. Returns the outcome of the review,
+    /// or `None` for memorization cards.
+    pub fn grade_card(&mut self, grade: Grade, deck_config: &DeckConfig) -> Option<ReviewOutcome> {
+        let current_date = chrono::Local::now().naive_utc();
 
-        let deck_durations = &deck_config.deck_intervals;
+        let current_item = self.queue.pop_front()?;
+        self.answered += 1;
+        self.mark_modified(current_item.dataset);
+        let (dataset, card_index, reverse) = (
+            current_item.dataset,
+            current_item.card,
+            current_item.reverse,
+        );
 
         let card_mut = &mut self.datasets[current_item.dataset].cards[current_item.card];
-        let current_deck = card_mut.get_deck(current_item.reverse).unwrap_or(0);
+        let old_deck = card_mut.get_deck(current_item.reverse);
+        let current_deck = old_deck.unwrap_or(0);
 
         // If in memorization mode, just remove the card from the queue
         if current_item.memorization_card {
-            card_mut.metadata = Some(VocabMetadata::default());
-            self.has_changes = true;
-            return;
+            if card_mut.metadata.is_none() {
+                self.introduced += 1;
+            }
+            card_mut.metadata = Some(VocabMetadata {
+                learned: Some(current_date),
+                ..Default::default()
+            });
+            self.refill_queue();
+            return None;
         }
-        let change_deck =
-            !matches!(self.filter_mode, FilterMode::All) || deck_config.change_deck_in_ignore_date;
-        if answer_correct {
-            let new_deck = if change_deck {
-                (current_deck + 1).min(deck_durations.len() as u8 - 1)
-            } else {
-                current_deck
-            };
-            card_mut.update_metadata(
-                new_deck,
-                current_date + deck_durations[new_deck as usize].0,
-                current_item.reverse,
-            );
+        let floor = wrong_answer_floor(card_mut, current_date, deck_config);
+        let first_review = card_mut.metadata.is_none();
+        card_mut.mark_reviewed(current_date, current_item.reverse);
+        let new_deck = graded_deck(current_deck, grade, floor, self.filter_mode, deck_config);
+        card_mut.update_metadata(
+            new_deck,
+            current_date + deck_config.interval(new_deck),
+            current_item.reverse,
+        );
+        if first_review && let Some(metadata) = &mut card_mut.metadata {
+            // Without a memorization round, cards are learned with their first review
+            metadata.learned = Some(current_date);
+            self.introduced += 1;
+        }
+        if grade == Grade::Wrong {
+            card_mut.lapses += 1;
+            // Repeated within the current batch, before any cards from the reserve
+            self.queue.push_back(current_item);
+        } else if self.initial_decks[dataset][card_index].is_none() {
+            self.completed_new += 1;
         } else {
-            let new_deck = if change_deck {
-                (current_deck as i16 - 1).max(0) as u8
-            } else {
-                current_deck
+            self.completed_reviews += 1;
+        }
+        // Only now the answered card counts as reviewed in this session and is left out
+        self.top_up_work_ahead();
+        self.refill_queue();
+        let card = &self.datasets[dataset].cards[card_index];
+        let metadata = card
+            .metadata
+            .as_ref()
+            .expect("Reviewed card without metadata");
+        let (deck, due_date) = if reverse {
+            (metadata.deck_reverse, metadata.due_date_reverse)
+        } else {
+            (metadata.deck, metadata.due_date)
+        };
+        let (query, answer) = if reverse {
+            (&card.word_b, &card.word_a)
+        } else {
+            (&card.word_a, &card.word_b)
+        };
+        let outcome = ReviewOutcome {
+            file_path: self.datasets[dataset].file_path.clone(),
+            query: query.base.clone(),
+            answer: answer.base.clone(),
+            reverse,
+            old_deck,
+            new_deck: deck,
+        };
+        if self.record_history {
+            let entry = ReviewEntry {
+                word_a: card.word_a.base.clone(),
+                word_b: card.word_b.base.clone(),
+                reverse,
+                reviewed: current_date,
+                deck,
+                due_date,
             };
-            card_mut.update_metadata(
-                new_deck,
-                current_date + deck_durations[new_deck as usize].0,
-                current_item.reverse,
-            );
-            self.queue.push_back(current_item);
+            self.history.push((dataset, entry));
         }
-        self.has_changes = true;
+        Some(outcome)
     }
 
-    #[inline]
-    pub fn current_progress(&self) -> usize {
-        self.total_tasks() - self.queue.len()
+    /// Intervals after which the current card would be due again if it was graded correct or
+    /// wrong. `None` for memorization cards.
+    pub fn preview_intervals(
+        &self,
+        deck_config: &DeckConfig,
+    ) -> Option<(chrono::Duration, chrono::Duration)> {
+        Some((
+            self.preview_interval(Grade::Correct, deck_config)?,
+            self.preview_interval(Grade::Wrong, deck_config)?,
+        ))
     }
 
-    #[inline]
-    pub fn total_tasks(&self) -> usize {
-        self.total_due
+    /// Interval after which the current card would be due again with `grade`. `None` for
+    /// memorization cards.
+    pub fn preview_interval(
+        &self,
+        grade: Grade,
+        deck_config: &DeckConfig,
+    ) -> Option<chrono::Duration> {
+        let index = self
+            .queue
+            .front()
+            .filter(|index| !index.memorization_card)?;
+        let card = &self.datasets[index.dataset].cards[index.card];
+        let current_deck = card.get_deck(index.reverse).unwrap_or(0);
+        let floor = wrong_answer_floor(card, chrono::Local::now().naive_utc(), deck_config);
+        let deck = graded_deck(current_deck, grade, floor, self.filter_mode, deck_config);
+        Some(deck_config.interval(deck))
     }
 
-    pub fn save(&self) -> Result<(), std::io::Error> {
-        for dataset in &self.datasets {
-            let file_path = &dataset.file_path;
-            let mut file = std::fs::File::create(file_path)?;
-            writeln!(file, "{}\t{}", dataset.lang_a, dataset.lang_b)?;
-            for card in &dataset.cards {
-                let line = match card.metadata {
-                    Some(ref metadata) => format!(
-                        "{}\t{}\t{}\t{}\t{}\t{}",
-                        card.word_a.base,
-                        card.word_b.base,
-                        metadata.deck,
-                        metadata.due_date.format("%Y-%m-%d %H:%M:%S"),
-                        metadata.deck_reverse,
-                        metadata.due_date_reverse.format("%Y-%m-%d %H:%M:%S")
-                    ),
-                    None => format!("{}\t{}", card.word_a.base, card.word_b.base),
-                };
-                writeln!(file, "{}", line)?;
-            }
-        }
-        Ok(())
+    /// All recorded reviews of the current card, including the unsaved ones of this session.
+    pub fn current_history(&self) -> Result<Vec<ReviewEntry>, VocaParseError> {
+        let Some(index) = self.queue.front() else {
+            return Ok(Vec::new());
+        };
+        let dataset = &self.datasets[index.dataset];
+        let card = &dataset.cards[index.card];
+        let is_current = |entry: &ReviewEntry| {
+            entry.word_a == card.word_a.base && entry.word_b == card.word_b.base
+        };
+        let mut entries = review_history::load(&dataset.file_path)?;
+        entries.retain(is_current);
+        entries.extend(
+            self.history
+                .iter()
+                .filter(|(i, entry)| *i == index.dataset && is_current(entry))
+                .map(|(_, entry)| entry.clone()),
+        );
+        Ok(entries)
     }
 
-    pub fn from_files(
-        file_paths: &[String],
-        filter_mode: FilterMode,
-        sort_mode: SortMode,
-        limit: Option<usize>,
-        memorization_config: &MemorizationConfig,
-    ) -> Result<Self, VocaParseError> {
-        let datasets = file_paths
+    /// Moves the `count` items in the highest decks to the front of the queue, so that the
+    /// session starts with well known cards.
+    pub fn warm_up(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let mut candidates = self
+            .queue
             .iter()
-            .map(|file_path| VocaCardDataset::from_file(file_path))
-            .collect::<Result<Vec<_>, VocaParseError>>()?;
-        Ok(VocaSession::new(
-            datasets,
-            filter_mode,
-            sort_mode,
-            limit,
-            memorization_config,
-        ))
+            .enumerate()
+            .filter_map(|(i, item)| {
+                let card = &self.datasets[item.dataset].cards[item.card];
+                Some((i, card.get_deck(item.reverse)?))
+            })
+            .collect::<Vec<_>>();
+        // Stable, so that items in the same deck keep the order of the sort mode
+        candidates.sort_by_key(|&(_, deck)| std::cmp::Reverse(deck));
+        let mut items = std::mem::take(&mut self.queue)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.queue = candidates
+            .into_iter()
+            .take(count)
+            .filter_map(|(i, _)| items[i].take())
+            .collect();
+        self.queue.extend(items.into_iter().flatten());
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::model::voca_card::VocabWord;
 
-    use super::*;
+    /// Removes the reverse direction of cards with identical words, or the whole card if they
+    /// are skipped, so that they do not count twice.
+    fn handle_identical_words(&mut self, handling: IdenticalWords) {
+        let datasets = &self.datasets;
+        let identical =
+            |item: &VocabItem| datasets[item.dataset].cards[item.card].has_identical_words();
+        match handling {
+            IdenticalWords::Both => return,
+            IdenticalWords::Forward => self
+                .queue
+                .retain(|item| !(item.reverse && !item.memorization_card && identical(item))),
+            IdenticalWords::Skip => self.queue.retain(|item| !identical(item)),
+        }
+        self.total_due = self.queue.len();
+    }
 
-    #[test]
-    fn test_sorting() {
-        let card1 = Vocab {
-            word_a: VocabWord::from_str("hello"),
-            word_b: VocabWord::from_str("hola"),
-            metadata: Some(VocabMetadata {
-                deck: 1,
-                due_date: chrono::NaiveDateTime::parse_from_str(
-                    "2023-10-01 12:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-                deck_reverse: 2,
-                due_date_reverse: chrono::NaiveDateTime::parse_from_str(
-                    "2024-10-01 13:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-            }),
-        };
-        let card2 = Vocab {
-            word_a: VocabWord::from_str("world"),
-            word_b: VocabWord::from_str("mundo"),
-            metadata: Some(VocabMetadata {
-                deck: 2,
-                due_date: chrono::NaiveDateTime::parse_from_str(
-                    "2023-09-01 12:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-                deck_reverse: 1,
-                due_date_reverse: chrono::NaiveDateTime::parse_from_str(
-                    "2024-09-01 13:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-            }),
-        };
-        let card3 = Vocab {
-            word_a: VocabWord::from_str("test"),
-            word_b: VocabWord::from_str("prueba"),
-            metadata: Some(VocabMetadata {
-                deck: 1,
-                due_date: chrono::NaiveDateTime::parse_from_str(
-                    "2023-08-01 12:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-                deck_reverse: 2,
-                due_date_reverse: chrono::NaiveDateTime::parse_from_str(
-                    "2024-08-01 13:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-            }),
-        };
+    /// Moves the reverse direction of every card right behind its normal direction.
+    /// Memorization cards keep their place at the start of the queue.
+    fn pair_directions(&mut self) {
+        let (reverse, rest): (Vec<_>, Vec<_>) = self
+            .queue
+            .drain(..)
+            .partition(|item| item.reverse && !item.memorization_card);
+        let reverse_index = reverse
+            .iter()
+            .enumerate()
+            .map(|(n, item)| ((item.dataset, item.card), n))
+            .collect::<HashMap<_, _>>();
+        let mut reverse = reverse.into_iter().map(Some).collect::<Vec<_>>();
+        for item in rest {
+            let pair = (!item.memorization_card)
+                .then(|| reverse_index.get(&(item.dataset, item.card)))
+                .flatten()
+                .and_then(|&n| reverse[n].take());
+            self.queue.push_back(item);
+            self.queue.extend(pair);
+        }
+        // Reverse directions whose normal direction is not due
+        self.queue.extend(reverse.into_iter().flatten());
+    }
 
-        let dataset = VocaCardDataset {
-            cards: vec![card1, card2, card3],
-            file_path: "test.txt".to_string(),
-            lang_a: "English".to_string(),
-            lang_b: "Spanish".to_string(),
-        };
+    /// Limits the queue to a batch of `batch_size` items and keeps the rest in reserve.
+    fn set_batch_size(&mut self, batch_size: Option<usize>) {
+        self.batch_size = batch_size;
+        if let Some(size) = batch_size
+            && self.queue.len() > size
+        {
+            let mut reserve = self.queue.split_off(size);
+            reserve.append(&mut self.reserve);
+            self.reserve = reserve;
+        }
+    }
 
-        let session = VocaSession::new(
-            vec![dataset.clone()],
-            FilterMode::All,
-            SortMode::DueDate,
-            None,
-            &MemorizationConfig::default(),
-        );
+    /// Moves the tested direction of the current card to the last deck, for words that are
+    /// already known.
+    pub fn mark_known(&mut self, deck_config: &DeckConfig) {
+        self.adjust_current_deck(i8::MAX, deck_config);
+    }
 
-        assert_eq!(session.queue.len(), 6);
-        assert_eq!(session.queue[0].card, 2); // "test"
-        assert_eq!(session.queue[1].card, 1); // "world"
-        assert_eq!(session.queue[2].card, 0); // "hello"
+    /// Moves items from the reserve into the queue until the batch is full again.
+    fn refill_queue(&mut self) {
+        while self.batch_size.is_none_or(|size| self.queue.len() < size)
+            && let Some(item) = self.reserve.pop_front()
+        {
+            self.queue.push_back(item);
+        }
+        // Continue with all datasets once the focused one is done
+        if self.queue.is_empty() && !self.unfocused.is_empty() {
+            self.set_focus(None);
+        }
+    }
 
-        let session = VocaSession::new(
-            vec![dataset],
-            FilterMode::All,
-            SortMode::Random,
-            None,
+    /// Seen directions that are due within the work-ahead horizon and were not answered in this
+    /// session, soonest first.
+    fn work_ahead_items(&self, horizon: chrono::Duration) -> Vec<VocabItem> {
+        let until = chrono::Local::now().naive_utc() + horizon;
+        let mut items = Vec::new();
+        for (i, dataset) in self.datasets.iter().enumerate() {
+            let directions: &[bool] = if dataset.overrides.forward_only {
+                &[false]
+            } else {
+                &[false, true]
+            };
+            for (j, card) in dataset.cards.iter().enumerate() {
+                let Some(metadata) = &card.metadata else {
+                    continue;
+                };
+                for &reverse in directions {
+                    let (due_date, last_reviewed) = if reverse {
+                        (metadata.due_date_reverse, metadata.last_reviewed_reverse)
+                    } else {
+                        (metadata.due_date, metadata.last_reviewed)
+                    };
+                    if due_date < until && last_reviewed.is_none_or(|date| date < self.started) {
+                        items.push((
+                            due_date,
+                            VocabItem {
+                                dataset: i,
+                                card: j,
+                                reverse,
+                                memorization_card: false,
+                                skips: 0,
+                            },
+                        ));
+                    }
+                }
+            }
+        }
+        items.sort_by_key(|(due_date, _)| *due_date);
+        items.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// Number of cards that working ahead would load, `None` if working ahead is disabled.
+    pub fn work_ahead_count(&self) -> Option<usize> {
+        self.work_ahead
+            .map(|horizon| self.work_ahead_items(horizon).len())
+    }
+
+    /// Adds the next batch of cards to work ahead on to the reserve once fewer tasks than the
+    /// low watermark are left, so that the session continues without waiting for it to end.
+    fn top_up_work_ahead(&mut self) {
+        let Some(horizon) = self.work_ahead else {
+            return;
+        };
+        if self.work_ahead_low_watermark == 0
+            || self.queue.len() + self.reserve.len() >= self.work_ahead_low_watermark
+        {
+            return;
+        }
+        let queued = self
+            .queue
+            .iter()
+            .chain(&self.reserve)
+            .chain(&self.unfocused)
+            .map(|item| (item.dataset, item.card, item.reverse))
+            .collect::<HashSet<_>>();
+        let items = self
+            .work_ahead_items(horizon)
+            .into_iter()
+            .filter(|item| !queued.contains(&(item.dataset, item.card, item.reverse)))
+            .take(self.work_ahead_batch_size.max(1))
+            .collect::<Vec<_>>();
+        self.total_due += items.len();
+        self.reserve.extend(items);
+    }
+
+    /// Loads the cards due within the work-ahead horizon once no card is left. They are graded
+    /// like due cards. Returns the number of loaded cards.
+    pub fn start_work_ahead(&mut self) -> usize {
+        let Some(horizon) = self.work_ahead else {
+            return 0;
+        };
+        if self.current_task().is_some() {
+            return 0;
+        }
+        let items = self.work_ahead_items(horizon);
+        let count = items.len();
+        self.total_due += count;
+        self.reserve.extend(items);
+        self.refill_queue();
+        count
+    }
+
+    /// Only tests the dataset with the index `focus` from now on, or all datasets again if it is
+    /// `None`. The items of the other datasets keep their order and still count as remaining.
+    pub fn set_focus(&mut self, focus: Option<usize>) {
+        let mut items = std::mem::take(&mut self.queue);
+        items.append(&mut self.reserve);
+        items.append(&mut self.unfocused);
+        (self.queue, self.unfocused) = items
+            .into_iter()
+            .partition(|item| focus.is_none_or(|dataset| item.dataset == dataset));
+        self.focus = focus;
+        self.set_batch_size(self.batch_size);
+    }
+
+    pub fn focus(&self) -> Option<usize> {
+        self.focus
+    }
+
+    pub fn file_paths(&self) -> Vec<&str> {
+        self.datasets
+            .iter()
+            .map(|dataset| dataset.file_path.as_str())
+            .collect()
+    }
+
+    /// Index of the file that the current card belongs to.
+    pub fn current_dataset(&self) -> Option<usize> {
+        self.queue.front().map(|item| item.dataset)
+    }
+
+    /// Whether the progress of `dataset` is never saved.
+    pub fn is_read_only(&self, dataset: usize) -> bool {
+        self.datasets[dataset].read_only
+    }
+
+    /// Names of the two languages of `dataset`.
+    pub fn languages(&self, dataset: usize) -> (&str, &str) {
+        let dataset = &self.datasets[dataset];
+        (&dataset.lang_a, &dataset.lang_b)
+    }
+
+    /// Adds a new card to `dataset`, which is written when the session is saved. With `study`,
+    /// its directions are tested at the end of the session.
+    pub fn add_card(
+        &mut self,
+        dataset: usize,
+        word_a: &str,
+        word_b: &str,
+        study: bool,
+        synonyms: &Synonyms,
+    ) -> Result<(), std::io::Error> {
+        if self.datasets[dataset].read_only {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("'{}' is read-only", self.datasets[dataset].file_path),
+            ));
+        }
+        let mut card = Vocab::new(
+            VocabWord::from_str(word_a),
+            VocabWord::from_str(word_b),
+            None,
+        );
+        for word in [&mut card.word_a, &mut card.word_b] {
+            // Unknown groups are kept as typed, like in the files
+            word.expand_synonyms(synonyms);
+        }
+        let cards = &mut self.datasets[dataset].cards;
+        cards.push(card);
+        let card = cards.len() - 1;
+        self.initial_decks[dataset].push(None);
+        self.mark_modified(dataset);
+        if !study {
+            return Ok(());
+        }
+        let directions: &[bool] = if self.datasets[dataset].overrides.forward_only {
+            &[false]
+        } else {
+            &[false, true]
+        };
+        for &reverse in directions {
+            let item = VocabItem {
+                dataset,
+                card,
+                reverse,
+                memorization_card: false,
+                skips: 0,
+            };
+            if self.focus.is_some_and(|focus| focus != dataset) {
+                self.unfocused.push_back(item);
+            } else {
+                self.requeue(item);
+            }
+            self.total_due += 1;
+        }
+        Ok(())
+    }
+
+    /// Puts an item at the end of the session, behind all reserved items.
+    fn requeue(&mut self, item: VocabItem) {
+        if self.reserve.is_empty() {
+            self.queue.push_back(item);
+        } else {
+            self.reserve.push_back(item);
+        }
+    }
+
+    /// Number of items that still have to be answered.
+    fn remaining(&self) -> usize {
+        self.queue.len() + self.reserve.len() + self.unfocused.len()
+    }
+
+    /// Whether the current card had never been answered when the session started. Covers its
+    /// memorization round and its first tests.
+    pub fn current_is_new(&self) -> bool {
+        self.queue
+            .front()
+            .is_some_and(|index| self.initial_decks[index.dataset][index.card].is_none())
+    }
+
+    /// Number of cards that were seen for the first time in this session.
+    pub fn introduced(&self) -> usize {
+        self.introduced
+    }
+
+    /// Passed directions of new cards and of reviewed cards in this session.
+    pub fn completed(&self) -> (usize, usize) {
+        (self.completed_new, self.completed_reviews)
+    }
+
+    /// Number of items of the current card that still have to be answered, including the
+    /// current one. Covers the other direction and repetitions after wrong answers.
+    pub fn remaining_for_current_card(&self) -> usize {
+        let Some(current) = self.queue.front() else {
+            return 0;
+        };
+        self.queue
+            .iter()
+            .chain(&self.reserve)
+            .filter(|item| (item.dataset, item.card) == (current.dataset, current.card))
+            .count()
+    }
+
+    #[inline]
+    pub fn current_progress(&self) -> usize {
+        match self.progress_mode {
+            ProgressMode::Fixed => self.total_due.saturating_sub(self.remaining()),
+            ProgressMode::Dynamic => self.answered,
+        }
+    }
+
+    /// Whether any card of the loaded files matches `--grep`, regardless of whether it is due.
+    pub fn any_card_matches(&self, text: &str) -> bool {
+        self.datasets
+            .iter()
+            .flat_map(|dataset| &dataset.cards)
+            .any(|card| card_matches(card, text))
+    }
+
+    #[inline]
+    pub fn total_tasks(&self) -> usize {
+        match self.progress_mode {
+            ProgressMode::Fixed => self.total_due,
+            ProgressMode::Dynamic => self.answered + self.remaining(),
+        }
+    }
+
+    /// Retention metrics of every dataset, based on the cards that have been seen before.
+    pub fn deck_health(&self) -> Vec<DeckHealth> {
+        self.datasets
+            .iter()
+            .map(|dataset| {
+                let forward_only = dataset.overrides.forward_only;
+                let seen = dataset
+                    .cards
+                    .iter()
+                    .filter_map(|card| card.metadata.as_ref().map(|metadata| (card, metadata)))
+                    .collect::<Vec<_>>();
+                let (average_deck, never_lapsed) = if seen.is_empty() {
+                    (0.0, 0.0)
+                } else {
+                    let directions = if forward_only { 1 } else { 2 };
+                    let deck_sum = seen
+                        .iter()
+                        .map(|(_, metadata)| {
+                            let reverse = if forward_only {
+                                0
+                            } else {
+                                metadata.deck_reverse
+                            };
+                            metadata.deck as f64 + reverse as f64
+                        })
+                        .sum::<f64>();
+                    let never_lapsed = seen.iter().filter(|(card, _)| card.lapses == 0).count();
+                    (
+                        deck_sum / (directions * seen.len()) as f64,
+                        never_lapsed as f64 / seen.len() as f64,
+                    )
+                };
+                DeckHealth {
+                    name: dataset.file_path.clone(),
+                    seen_cards: seen.len(),
+                    average_deck,
+                    never_lapsed,
+                }
+            })
+            .collect()
+    }
+
+    /// Number of directions that become due on each of the next `days` days, starting with
+    /// today. Overdue directions count towards today.
+    pub fn projected_reviews(&self, now: NaiveDateTime, days: usize) -> Vec<usize> {
+        let mut reviews = vec![0; days];
+        let metadata = self.datasets.iter().flat_map(|dataset| {
+            let forward_only = dataset.overrides.forward_only;
+            dataset
+                .cards
+                .iter()
+                .filter_map(move |card| card.metadata.as_ref().map(|m| (m, forward_only)))
+        });
+        for (metadata, forward_only) in metadata {
+            let due_dates = if forward_only {
+                &[metadata.due_date][..]
+            } else {
+                &[metadata.due_date, metadata.due_date_reverse][..]
+            };
+            for &due_date in due_dates {
+                let day = (due_date.date() - now.date()).num_days().max(0) as usize;
+                if let Some(count) = reviews.get_mut(day) {
+                    *count += 1;
+                }
+            }
+        }
+        reviews
+    }
+
+    /// Moves all cards of the current card's file that reached `min_deck` in both directions
+    /// into a new file at `file_path`. The session continues with the moved cards and both files
+    /// are written when the session is saved. Returns the number of moved cards.
+    pub fn split_current_dataset(
+        &mut self,
+        min_deck: u8,
+        file_path: &str,
+    ) -> Result<usize, std::io::Error> {
+        let Some(source) = self.queue.front().map(|item| item.dataset) else {
+            return Ok(0);
+        };
+        if self.datasets[source].read_only {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("'{}' is read-only", self.datasets[source].file_path),
+            ));
+        }
+        if std::fs::exists(file_path)?
+            || self
+                .datasets
+                .iter()
+                .any(|dataset| dataset.file_path == file_path)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("'{}' already exists", file_path),
+            ));
+        }
+        let target = self.datasets.len();
+        let dataset = &mut self.datasets[source];
+        let forward_only = dataset.overrides.forward_only;
+        let (moved, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut dataset.cards)
+            .into_iter()
+            .zip(std::mem::take(&mut self.initial_decks[source]))
+            .enumerate()
+            .partition(|(_, (card, _))| card.is_learned(min_deck, forward_only));
+        // New dataset and index of every card of the source file
+        let mut new_indices = vec![(source, 0); moved.len() + kept.len()];
+        for (new_dataset, cards) in [(target, &moved), (source, &kept)] {
+            for (new_index, (old_index, _)) in cards.iter().enumerate() {
+                new_indices[*old_index] = (new_dataset, new_index);
+            }
+        }
+        let num_moved = moved.len();
+        let (kept_cards, kept_decks) = kept.into_iter().map(|(_, card)| card).unzip();
+        let (moved_cards, moved_decks) = moved.into_iter().map(|(_, card)| card).unzip();
+        dataset.cards = kept_cards;
+        self.initial_decks[source] = kept_decks;
+        if num_moved == 0 {
+            return Ok(0);
+        }
+
+        let mut new_dataset = VocaCardDataset::new(file_path, &dataset.lang_a, &dataset.lang_b);
+        new_dataset.cards = moved_cards;
+        new_dataset.lang_code_a = dataset.lang_code_a.clone();
+        new_dataset.lang_code_b = dataset.lang_code_b.clone();
+        new_dataset.overrides = dataset.overrides.clone();
+        new_dataset.aligned = dataset.aligned;
+        new_dataset.line_ending = dataset.line_ending;
+        new_dataset.encoding = dataset.encoding;
+        self.datasets.push(new_dataset);
+        self.initial_decks.push(moved_decks);
+        for item in self
+            .queue
+            .iter_mut()
+            .chain(self.reserve.iter_mut())
+            .chain(self.unfocused.iter_mut())
+        {
+            if item.dataset == source {
+                (item.dataset, item.card) = new_indices[item.card];
+            }
+        }
+        self.modified_datasets.extend([source, target]);
+        Ok(num_moved)
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        for &i in &self.modified_datasets {
+            self.datasets[i].save()?;
+            let entries = self
+                .history
+                .iter()
+                .filter(|(dataset, _)| *dataset == i)
+                .map(|(_, entry)| entry.clone())
+                .collect::<Vec<_>>();
+            review_history::append(&self.datasets[i].file_path, &entries)?;
+        }
+        Ok(())
+    }
+
+    pub fn from_files(
+        file_paths: &[String],
+        options: &SessionOptions,
+        config: &AppConfig,
+    ) -> Result<Self, VocaParseError> {
+        let mut datasets = file_paths
+            .iter()
+            .map(|file_path| {
+                let (file_path, read_only) = split_read_only(file_path);
+                // Downloaded decks are saved as local copies if a directory is given. Once the
+                // copy exists, it is studied instead of downloading the deck again.
+                let local_copy =
+                    options
+                        .save_to
+                        .as_ref()
+                        .filter(|_| is_url(file_path))
+                        .map(|save_to| {
+                            let file_name = file_path
+                                .rsplit('/')
+                                .find(|segment| !segment.is_empty())
+                                .unwrap_or("deck.tsv");
+                            save_to.join(file_name).to_string_lossy().to_string()
+                        });
+                let mut dataset = match local_copy {
+                    Some(local_copy) if std::fs::exists(&local_copy)? => {
+                        VocaCardDataset::load(&local_copy, config.session.encoding)?
+                    }
+                    Some(local_copy) => {
+                        let mut dataset =
+                            VocaCardDataset::load(file_path, config.session.encoding)?;
+                        dataset.file_path = local_copy;
+                        dataset.read_only = false;
+                        dataset
+                    }
+                    None => VocaCardDataset::load(file_path, config.session.encoding)?,
+                };
+                dataset.read_only |=
+                    read_only || options.read_only.iter().any(|path| path == file_path);
+                Ok(dataset)
+            })
+            .collect::<Result<Vec<_>, VocaParseError>>()?;
+        for dataset in &mut datasets {
+            dataset.resolve_language_codes(&config.languages);
+            dataset.overrides = config.deck_overrides.get(&dataset.file_path);
+            dataset.expand_synonyms(&config.synonyms);
+            dataset.apply_save_settings(&config.session);
+        }
+        let mut session = VocaSession::new(datasets, options, &config.memorization);
+        session.progress_mode = config.session.progress_mode;
+        session.handle_identical_words(config.session.identical_words);
+        if let Pairing::Adjacent = config.session.pairing {
+            session.pair_directions();
+        }
+        session.warm_up(config.session.warmup_count);
+        session.set_batch_size(config.session.study_batch_size);
+        session.work_ahead_low_watermark = config.session.work_ahead_low_watermark;
+        session.work_ahead_batch_size = config.session.work_ahead_batch_size;
+        session.record_history = config.session.record_history;
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::{DeckInverval, DeckOverride, FileEncoding, LineEnding},
+        model::voca_card::DATE_FORMAT,
+        parse_deck_range,
+    };
+
+    use super::*;
+
+    fn test_card(word_a: &str, word_b: &str, metadata: Option<VocabMetadata>) -> Vocab {
+        Vocab::new(
+            VocabWord::from_str(word_a),
+            VocabWord::from_str(word_b),
+            metadata,
+        )
+    }
+
+    #[test]
+    fn test_sorting() {
+        let card1 = test_card(
+            "hello",
+            "hola",
+            Some(VocabMetadata {
+                deck: 1,
+                due_date: chrono::NaiveDateTime::parse_from_str("2023-10-01 12:00:00", DATE_FORMAT)
+                    .unwrap(),
+                deck_reverse: 2,
+                due_date_reverse: chrono::NaiveDateTime::parse_from_str(
+                    "2024-10-01 13:00:00",
+                    DATE_FORMAT,
+                )
+                .unwrap(),
+                ..Default::default()
+            }),
+        );
+        let card2 = test_card(
+            "world",
+            "mundo",
+            Some(VocabMetadata {
+                deck: 2,
+                due_date: chrono::NaiveDateTime::parse_from_str("2023-09-01 12:00:00", DATE_FORMAT)
+                    .unwrap(),
+                deck_reverse: 1,
+                due_date_reverse: chrono::NaiveDateTime::parse_from_str(
+                    "2024-09-01 13:00:00",
+                    DATE_FORMAT,
+                )
+                .unwrap(),
+                ..Default::default()
+            }),
+        );
+        let card3 = test_card(
+            "test",
+            "prueba",
+            Some(VocabMetadata {
+                deck: 1,
+                due_date: chrono::NaiveDateTime::parse_from_str("2023-08-01 12:00:00", DATE_FORMAT)
+                    .unwrap(),
+                deck_reverse: 2,
+                due_date_reverse: chrono::NaiveDateTime::parse_from_str(
+                    "2024-08-01 13:00:00",
+                    DATE_FORMAT,
+                )
+                .unwrap(),
+                ..Default::default()
+            }),
+        );
+
+        let dataset = VocaCardDataset {
+            cards: vec![card1, card2, card3],
+            file_path: "test.txt".to_string(),
+            lang_a: "English".to_string(),
+            lang_b: "Spanish".to_string(),
+            lang_code_a: None,
+            lang_code_b: None,
+            compressed: false,
+            overrides: DeckOverride::default(),
+            aligned: false,
+            uniform_columns: false,
+            line_ending: LineEnding::default(),
+            encoding: FileEncoding::default(),
+            read_only: false,
+        };
+
+        let session = VocaSession::new(
+            vec![dataset.clone()],
+            &SessionOptions {
+                filter_mode: FilterMode::All,
+                sort_mode: SortMode::DueDate,
+                ..Default::default()
+            },
+            &MemorizationConfig::default(),
+        );
+
+        assert_eq!(session.queue.len(), 6);
+        assert_eq!(session.queue[0].card, 2); // "test"
+        assert_eq!(session.queue[1].card, 1); // "world"
+        assert_eq!(session.queue[2].card, 0); // "hello"
+
+        let mut reviewed = dataset.clone();
+        for (card, day) in reviewed.cards.iter_mut().zip([3, 1, 2]) {
+            let date = chrono::NaiveDate::from_ymd_opt(2023, 7, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap();
+            card.mark_reviewed(date, false);
+            card.mark_reviewed(date, true);
+        }
+        // Only reviewed in one direction, so it counts as never seen
+        reviewed.cards[0]
+            .metadata
+            .as_mut()
+            .unwrap()
+            .last_reviewed_reverse = None;
+        let session = VocaSession::new(
+            vec![reviewed],
+            &SessionOptions {
+                filter_mode: FilterMode::All,
+                sort_mode: SortMode::LastSeen,
+                ..Default::default()
+            },
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.queue[0].card, 0); // "hello"
+        assert_eq!(session.queue[1].card, 1); // "world"
+        assert_eq!(session.queue[2].card, 2); // "test"
+
+        let session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions {
+                filter_mode: FilterMode::All,
+                sort_mode: SortMode::Random,
+                ..Default::default()
+            },
             &MemorizationConfig::default(),
         );
 
@@ -414,17 +1697,1399 @@ mod tests {
     fn vocab_validation() {
         let task = VocabTask {
             query: "hello",
+            query_reading: None,
             answer: "hola",
-            answer_variants: &vec!["hola".to_string(), "saludo".to_string()],
+            answer_variants: &["hola".to_string(), "saludo".to_string()],
+            answer_components: &[],
+            answer_hints: &[],
             show_answer: false,
+            reverse: false,
+            target_lang: "Spanish",
+            exact_match: false,
+            strict_diacritics: false,
+            hint: None,
         };
         let val_config = ValidationConfig {
             error_tolerance: 1,
             tolerance_min_length: 3,
+            ..Default::default()
         };
         assert!(task.is_correct("hola", &val_config));
         assert!(task.is_correct("hola!", &val_config));
         assert!(task.is_correct("saludo", &val_config));
         assert!(!task.is_correct("hello", &val_config));
+
+        let task = VocabTask {
+            exact_match: true,
+            ..task
+        };
+        assert!(task.is_correct("saludo", &val_config));
+        assert!(!task.is_correct("hola!", &val_config));
+
+        let task = VocabTask {
+            reverse: true,
+            exact_match: false,
+            ..task
+        };
+        let val_config = ValidationConfig {
+            error_tolerance_reverse: Some(0),
+            ..val_config
+        };
+        assert!(!task.is_correct("saludos", &val_config));
+        assert!(task.is_correct("saludo", &val_config));
+        assert!(!task.is_correct("Saludo", &val_config));
+
+        let val_config = ValidationConfig {
+            ignore_leading_case: vec!["Spanish".to_string()],
+            ..val_config
+        };
+        assert!(task.is_correct("Saludo", &val_config));
+        assert!(!task.is_correct("SAludo", &val_config));
+
+        let val_config = ValidationConfig {
+            phonetic_match: vec!["Spanish".to_string()],
+            ..val_config
+        };
+        assert!(task.is_correct("salludo", &val_config));
+        // Vowels are not coded, only the consonants have to sound alike
+        assert!(task.is_correct("selodo", &val_config));
+        assert!(!task.is_correct("salmo", &val_config));
+    }
+
+    #[test]
+    fn whitespace_in_answers() {
+        let answer = VocabWord::from_str("hola, buenos días");
+        let task = VocabTask {
+            query: "hello",
+            query_reading: None,
+            answer: &answer.base,
+            answer_variants: &answer.variants,
+            answer_components: &answer.components,
+            answer_hints: &answer.hints,
+            show_answer: false,
+            reverse: false,
+            target_lang: "Spanish",
+            exact_match: false,
+            strict_diacritics: false,
+            hint: None,
+        };
+        let mut val_config = ValidationConfig::default();
+        // "hola" is too short for any tolerance, so the spaces would be typos
+        assert!(task.is_correct(" hola ", &val_config));
+        assert!(!task.is_correct("buenos    días", &val_config));
+        val_config.collapse_spaces = true;
+        assert!(task.is_correct(" buenos   días", &val_config));
+        val_config.collapse_spaces = false;
+        val_config.trim_input = false;
+        assert!(!task.is_correct("hola ", &val_config));
+        // Longer words tolerate the spaces anyway
+        assert!(task.is_correct("buenos días ", &val_config));
+    }
+
+    #[test]
+    fn optional_articles() {
+        fn task<'a>(answer: &'a VocabWord, target_lang: &'a str) -> VocabTask<'a> {
+            VocabTask {
+                query: "",
+                query_reading: None,
+                answer: &answer.base,
+                answer_variants: &answer.variants,
+                answer_components: &answer.components,
+                answer_hints: &answer.hints,
+                show_answer: false,
+                reverse: false,
+                target_lang,
+                exact_match: false,
+                strict_diacritics: false,
+                hint: None,
+            }
+        }
+        let articles = |articles: &[&str]| articles.iter().map(|a| a.to_string()).collect();
+        let val_config = ValidationConfig {
+            error_tolerance: 0,
+            articles: HashMap::from([
+                ("German".to_string(), articles(&["der", "die", "das"])),
+                ("French".to_string(), articles(&["le", "la", "les", "l'"])),
+                ("Spanish".to_string(), articles(&["el", "la", "los", "las"])),
+            ]),
+            ..Default::default()
+        };
+
+        let tisch = VocabWord::from_str("der Tisch");
+        assert!(task(&tisch, "German").is_correct("Tisch", &val_config));
+        assert!(task(&tisch, "German").is_correct("der Tisch", &val_config));
+        assert!(!task(&tisch, "German").is_correct("die Tisch", &val_config));
+        // Only in the configured languages
+        assert!(!task(&tisch, "English").is_correct("Tisch", &val_config));
+        let haus = VocabWord::from_str("Haus");
+        assert!(task(&haus, "German").is_correct("das Haus", &val_config));
+        // A lone article is a word of its own
+        let die = VocabWord::from_str("die");
+        assert!(!task(&die, "German").is_correct("", &val_config));
+
+        let arbre = VocabWord::from_str("l'arbre");
+        assert!(task(&arbre, "French").is_correct("arbre", &val_config));
+        let maison = VocabWord::from_str("maison");
+        assert!(task(&maison, "French").is_correct("La maison", &val_config));
+        // "le" is no article of "lecteur"
+        let lecteur = VocabWord::from_str("lecteur");
+        assert!(!task(&lecteur, "French").is_correct("cteur", &val_config));
+
+        let casa = VocabWord::from_str("la casa");
+        assert!(task(&casa, "Spanish").is_correct("casa", &val_config));
+        assert!(!task(&casa, "Spanish").is_correct("el casa", &val_config));
+    }
+
+    #[test]
+    fn component_answers() {
+        let answer = VocabWord::from_str("der & Schreibtisch");
+        let task = VocabTask {
+            query: "desk",
+            query_reading: None,
+            answer: &answer.base,
+            answer_variants: &answer.variants,
+            answer_components: &answer.components,
+            answer_hints: &answer.hints,
+            show_answer: false,
+            reverse: false,
+            target_lang: "German",
+            exact_match: false,
+            strict_diacritics: false,
+            hint: None,
+        };
+        let val_config = ValidationConfig::default();
+        assert!(task.is_correct("der Schreibtisch", &val_config));
+        assert!(task.is_correct("Schreibtish der", &val_config));
+        // Short components get no tolerance
+        assert!(!task.is_correct("die Schreibtisch", &val_config));
+        assert!(!task.is_correct("Schreibtisch", &val_config));
+    }
+
+    #[test]
+    fn collapse_repeats() {
+        fn task(answer: &VocabWord) -> VocabTask<'_> {
+            VocabTask {
+                query: "",
+                query_reading: None,
+                answer: &answer.base,
+                answer_variants: &answer.variants,
+                answer_components: &answer.components,
+                answer_hints: &answer.hints,
+                show_answer: false,
+                reverse: false,
+                target_lang: "German",
+                exact_match: false,
+                strict_diacritics: false,
+                hint: None,
+            }
+        }
+        let mut val_config = ValidationConfig {
+            error_tolerance: 0,
+            ..Default::default()
+        };
+        let hallo = VocabWord::from_str("hallo");
+        assert!(!task(&hallo).is_correct("halo", &val_config));
+        val_config.collapse_repeats = true;
+        assert!(task(&hallo).is_correct("halo", &val_config));
+        assert!(task(&hallo).is_correct("hallloo", &val_config));
+        // Doubled letters no longer tell words apart, but other letters still do
+        assert!(task(&VocabWord::from_str("perro")).is_correct("pero", &val_config));
+        let hoehle = VocabWord::from_str("Höhle");
+        assert!(!task(&hoehle).is_correct("Hölle", &val_config));
+        assert!(!task(&hoehle).is_correct("Höle", &val_config));
+        assert_eq!(super::collapse_repeats("Schifffahrt"), "Schifahrt");
+    }
+
+    #[test]
+    fn strict_diacritics() {
+        let answer = VocabWord::from_str("buenos días");
+        let task = VocabTask {
+            query: "good morning",
+            query_reading: None,
+            answer: &answer.base,
+            answer_variants: &answer.variants,
+            answer_components: &answer.components,
+            answer_hints: &answer.hints,
+            show_answer: false,
+            reverse: false,
+            target_lang: "Spanish",
+            exact_match: false,
+            strict_diacritics: false,
+            hint: None,
+        };
+        let mut val_config = ValidationConfig::default();
+        assert!(task.is_correct("buenos dias", &val_config));
+        val_config.strict_diacritics = true;
+        assert!(!task.is_correct("buenos dias", &val_config));
+        assert!(!task.is_correct("buenos dìas", &val_config));
+        // Other typos are still forgiven
+        assert!(task.is_correct("buenos díaz", &val_config));
+        assert!(task.is_correct("Buenos Días", &val_config));
+
+        let task = VocabTask {
+            strict_diacritics: true,
+            ..task
+        };
+        assert!(!task.is_correct("buenos dias", &ValidationConfig::default()));
+        assert!(types_diacritics("дом", "дом"));
+        assert!(!types_diacritics("uber", "über"));
+    }
+
+    #[test]
+    fn listed_variants() {
+        let answer = VocabWord::from_str("(to) greet, hail");
+        let task = VocabTask {
+            query: "grüßen",
+            query_reading: None,
+            answer: &answer.base,
+            answer_variants: &answer.variants,
+            answer_components: &answer.components,
+            answer_hints: &answer.hints,
+            show_answer: false,
+            reverse: false,
+            target_lang: "Spanish",
+            exact_match: false,
+            strict_diacritics: false,
+            hint: None,
+        };
+        assert_eq!(task.listed_variants(), vec!["(to) greet", "hail"]);
+
+        let answer = VocabWord::from_str("hola [informal], saludo");
+        let task = VocabTask {
+            answer: &answer.base,
+            answer_variants: &answer.variants,
+            ..task
+        };
+        assert_eq!(task.listed_variants(), vec!["hola [informal]", "saludo"]);
+        assert!(task.is_correct("hola", &ValidationConfig::default()));
+    }
+
+    #[test]
+    fn skip_limit() {
+        let card = test_card("hello", "hola", Some(VocabMetadata::default()));
+        let dataset = VocaCardDataset {
+            cards: vec![card],
+            file_path: "test.txt".to_string(),
+            lang_a: "English".to_string(),
+            lang_b: "Spanish".to_string(),
+            lang_code_a: None,
+            lang_code_b: None,
+            compressed: false,
+            overrides: DeckOverride::default(),
+            aligned: false,
+            uniform_columns: false,
+            line_ending: LineEnding::default(),
+            encoding: FileEncoding::default(),
+            read_only: false,
+        };
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.queue.len(), 2);
+
+        session.skip_card(Some(2));
+        assert_eq!(session.queue.len(), 2);
+        session.skip_card(Some(2));
+        assert_eq!(session.queue.len(), 2);
+        // The forward direction is skipped for the second time and gets buried
+        session.skip_card(Some(2));
+        assert_eq!(session.queue.len(), 1);
+        assert!(session.queue[0].reverse);
+
+        let card = &session.datasets[0].cards[0];
+        assert_eq!(card.skip_count, 3);
+        assert!(!card.is_due(false, FilterMode::Normal, chrono::Local::now().naive_utc()));
+        // Buried until the day is over
+        assert_eq!(
+            card.metadata.as_ref().unwrap().due_date,
+            start_of_next_day()
+        );
+        assert!(session.has_changes());
+    }
+
+    #[test]
+    fn due_date_of_current_card() {
+        let due_date =
+            chrono::NaiveDateTime::parse_from_str("2023-10-01 12:00:00", DATE_FORMAT).unwrap();
+        let scheduled = test_card(
+            "hello",
+            "hola",
+            Some(VocabMetadata {
+                deck: 1,
+                due_date,
+                deck_reverse: 0,
+                due_date_reverse: DateTime::UNIX_EPOCH.naive_utc(),
+                ..Default::default()
+            }),
+        );
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        dataset.cards.push(scheduled);
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.current_due_date(), Some(due_date));
+        session.skip_card(None);
+        // The reverse direction only finished the memorization round
+        assert_eq!(session.current_due_date(), None);
+    }
+
+    #[test]
+    fn random_subset() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for i in 0..10 {
+            dataset.cards.push(test_card(
+                &format!("word {}", i),
+                &format!("palabra {}", i),
+                // Not due for a long time
+                Some(VocabMetadata {
+                    due_date: chrono::NaiveDate::from_ymd_opt(2100, 1, 1)
+                        .unwrap()
+                        .and_hms_opt(0, 0, 0)
+                        .unwrap(),
+                    due_date_reverse: chrono::NaiveDate::from_ymd_opt(2100, 1, 1)
+                        .unwrap()
+                        .and_hms_opt(0, 0, 0)
+                        .unwrap(),
+                    ..Default::default()
+                }),
+            ));
+        }
+        let options = SessionOptions {
+            random_subset: Some(3),
+            seed: Some(42),
+            ..Default::default()
+        };
+        let cards = |session: &VocaSession| {
+            session
+                .queue
+                .iter()
+                .map(|item| item.card)
+                .collect::<Vec<_>>()
+        };
+        let session = VocaSession::new(
+            vec![dataset.clone()],
+            &options,
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.queue.len(), 6);
+        // The same seed selects the same cards
+        let same_seed = VocaSession::new(vec![dataset], &options, &MemorizationConfig::default());
+        assert_eq!(cards(&session), cards(&same_seed));
+    }
+
+    #[test]
+    fn memorization_threshold() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for word in ["hello", "world"] {
+            dataset.cards.push(test_card(word, word, None));
+        }
+        let session = VocaSession::new(
+            vec![dataset.clone()],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.queue.len(), 6);
+        assert!(session.queue[0].memorization_card);
+
+        let session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig {
+                memorization_min_new_cards: 3,
+                ..Default::default()
+            },
+        );
+        assert_eq!(session.queue.len(), 4);
+        assert!(session.queue.iter().all(|item| !item.memorization_card));
+    }
+
+    #[test]
+    fn weighted_direction() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for i in 0..100 {
+            dataset.cards.push(test_card(
+                &format!("word {}", i),
+                &format!("palabra {}", i),
+                Some(VocabMetadata {
+                    deck: 4,
+                    deck_reverse: 0,
+                    ..Default::default()
+                }),
+            ));
+        }
+        let session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions {
+                weighted_direction: true,
+                seed: Some(42),
+                ..Default::default()
+            },
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.queue.len(), 100);
+        let reverse = session.queue.iter().filter(|item| item.reverse).count();
+        // The reverse direction is in the lower deck and is picked with a probability of 5/6
+        assert!(reverse > 70, "{} of 100 cards were reversed", reverse);
+    }
+
+    #[test]
+    fn progress_with_requeued_card() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        dataset
+            .cards
+            .push(test_card("hello", "hola", Some(VocabMetadata::default())));
+        let deck_config = DeckConfig::default();
+        let new_session = |progress_mode| {
+            let mut session = VocaSession::new(
+                vec![dataset.clone()],
+                &SessionOptions::default(),
+                &MemorizationConfig::default(),
+            );
+            session.progress_mode = progress_mode;
+            session
+        };
+
+        let mut session = new_session(ProgressMode::Fixed);
+        session.next_card(false, &deck_config);
+        assert_eq!((session.current_progress(), session.total_tasks()), (0, 2));
+        session.next_card(true, &deck_config);
+        assert_eq!((session.current_progress(), session.total_tasks()), (1, 2));
+
+        let mut session = new_session(ProgressMode::Dynamic);
+        session.next_card(false, &deck_config);
+        assert_eq!((session.current_progress(), session.total_tasks()), (1, 3));
+        session.next_card(true, &deck_config);
+        session.next_card(true, &deck_config);
+        assert_eq!((session.current_progress(), session.total_tasks()), (3, 3));
+    }
+
+    #[test]
+    fn preview_intervals() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        dataset.cards.push(test_card(
+            "hello",
+            "hola",
+            Some(VocabMetadata {
+                deck: 1,
+                ..Default::default()
+            }),
+        ));
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        let intervals = &deck_config.deck_intervals;
+        assert_eq!(
+            session.preview_intervals(&deck_config),
+            Some((intervals[2].0, intervals[0].0))
+        );
+        // The preview does not change the card
+        session.next_card(true, &deck_config);
+        assert_eq!(session.datasets[0].cards[0].get_deck(false), Some(2));
+    }
+
+    #[test]
+    fn grace_period() {
+        let now = chrono::Local::now().naive_utc();
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for learned_days_ago in [1, 10] {
+            dataset.cards.push(test_card(
+                "hello",
+                "hola",
+                Some(VocabMetadata {
+                    deck: 1,
+                    deck_reverse: 1,
+                    learned: Some(now - chrono::Duration::days(learned_days_ago)),
+                    ..Default::default()
+                }),
+            ));
+        }
+        dataset.cards.push(test_card("new", "nuevo", None));
+        let deck_config = DeckConfig {
+            grace_period: Some(DeckInverval(chrono::Duration::days(3))),
+            ..Default::default()
+        };
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        // The memorization card of the new card comes first
+        session.next_card(true, &deck_config);
+        assert_eq!(
+            session.preview_intervals(&deck_config),
+            Some((
+                deck_config.deck_intervals[2].0,
+                deck_config.deck_intervals[1].0
+            ))
+        );
+        // Both directions of all three cards
+        for _ in 0..6 {
+            session.next_card(false, &deck_config);
+        }
+        let cards = &session.datasets[0].cards;
+        assert_eq!(
+            (cards[0].get_deck(false), cards[0].get_deck(true)),
+            (Some(1), Some(1))
+        );
+        assert_eq!(
+            (cards[1].get_deck(false), cards[1].get_deck(true)),
+            (Some(0), Some(0))
+        );
+        // The memorization round counts as learning the card
+        assert!(cards[2].metadata.as_ref().unwrap().learned.is_some());
+    }
+
+    #[test]
+    fn adjust_deck() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        dataset.cards.push(test_card(
+            "hello",
+            "hola",
+            Some(VocabMetadata {
+                deck: 1,
+                ..Default::default()
+            }),
+        ));
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        session.adjust_current_deck(-5, &deck_config);
+        assert_eq!(session.queue.len(), 1);
+        session.mark_known(&deck_config);
+        assert!(session.queue.is_empty());
+        assert!(session.has_changes());
+
+        let card = &session.datasets[0].cards[0];
+        assert_eq!(card.get_deck(false), Some(0));
+        let last_deck = deck_config.deck_intervals.len() as u8 - 1;
+        assert_eq!(card.get_deck(true), Some(last_deck));
+
+        let summary = session.change_summary();
+        assert_eq!((summary.changed_deck, summary.newly_seen), (1, 0));
+        assert_eq!(summary.files, vec!["test.txt".to_string()]);
+    }
+
+    #[test]
+    fn identical_words() {
+        let session = |handling| {
+            let mut dataset = VocaCardDataset::new("test.txt", "English", "German");
+            for (word_a, word_b) in [("Berlin", "Berlin "), ("house", "Haus")] {
+                dataset
+                    .cards
+                    .push(test_card(word_a, word_b, Some(VocabMetadata::default())));
+            }
+            let mut session = VocaSession::new(
+                vec![dataset],
+                &SessionOptions::default(),
+                &MemorizationConfig::default(),
+            );
+            session.handle_identical_words(handling);
+            let items = session
+                .queue
+                .iter()
+                .map(|item| (item.card, item.reverse))
+                .collect::<Vec<_>>();
+            (items, session.total_due)
+        };
+        assert_eq!(
+            session(IdenticalWords::Both),
+            (vec![(0, false), (1, false), (0, true), (1, true)], 4)
+        );
+        assert_eq!(
+            session(IdenticalWords::Forward),
+            (vec![(0, false), (1, false), (1, true)], 3)
+        );
+        assert_eq!(
+            session(IdenticalWords::Skip),
+            (vec![(1, false), (1, true)], 2)
+        );
+    }
+
+    #[test]
+    fn adjacent_pairing() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        let future = chrono::Local::now().naive_utc() + chrono::Duration::days(1);
+        for (word, metadata) in [
+            ("seen", Some(VocabMetadata::default())),
+            ("unseen", None),
+            (
+                "reverse only",
+                Some(VocabMetadata {
+                    due_date: future,
+                    ..Default::default()
+                }),
+            ),
+        ] {
+            dataset.cards.push(test_card(word, word, metadata));
+        }
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        session.pair_directions();
+        let order = session
+            .queue
+            .iter()
+            .map(|item| (item.card, item.reverse, item.memorization_card))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (1, false, true),
+                (0, false, false),
+                (0, true, false),
+                (1, false, false),
+                (1, true, false),
+                (2, true, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn forward_only_deck() {
+        let mut dataset = VocaCardDataset::new("test.txt", "Kanji", "Reading");
+        dataset.overrides.forward_only = true;
+        for metadata in [Some(VocabMetadata::default()), None] {
+            dataset.cards.push(test_card("山", "やま", metadata));
+        }
+        let session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig {
+                memorization_reversed: true,
+                ..Default::default()
+            },
+        );
+        assert!(session.queue.iter().all(|item| !item.reverse));
+        assert_eq!(session.queue.len(), 3);
+    }
+
+    #[test]
+    fn forward_only_statistics() {
+        let now = chrono::Local::now().naive_utc();
+        let mut dataset = VocaCardDataset::new("test.txt", "Kanji", "Reading");
+        dataset.overrides.forward_only = true;
+        // The reverse direction was never answered
+        dataset.cards.push(test_card(
+            "山",
+            "やま",
+            Some(VocabMetadata {
+                deck: 4,
+                due_date: now + chrono::Duration::days(1),
+                ..Default::default()
+            }),
+        ));
+        let session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.projected_reviews(now, 2), vec![0, 1]);
+        assert_eq!(session.deck_health()[0].average_deck, 4.0);
+        let card = &session.datasets[0].cards[0];
+        assert!(card.is_learned(4, true));
+        assert!(!card.is_learned(4, false));
+    }
+
+    #[test]
+    fn remaining_for_current_card() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for word in ["hello", "bye"] {
+            dataset
+                .cards
+                .push(test_card(word, word, Some(VocabMetadata::default())));
+        }
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.remaining_for_current_card(), 2);
+        session.next_card(false, &deck_config);
+        session.next_card(true, &deck_config);
+        // Both directions of the first card are left, one of them is a repetition
+        assert_eq!(session.queue[0].card, 0);
+        assert_eq!(session.remaining_for_current_card(), 2);
+        session.next_card(true, &deck_config);
+        session.next_card(true, &deck_config);
+        assert_eq!(session.remaining_for_current_card(), 1);
+    }
+
+    #[test]
+    fn new_card_counts() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for metadata in [Some(VocabMetadata::default()), None] {
+            dataset.cards.push(test_card("hello", "hola", metadata));
+        }
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        // Memorization round of the new card, then the forward directions of both cards
+        let mut is_new = Vec::new();
+        for correct in [true, true, false, true] {
+            is_new.push(session.current_is_new());
+            session.next_card(correct, &deck_config);
+        }
+        assert_eq!(is_new, vec![true, false, true, false]);
+        // The memorization round and the wrong answer do not count
+        assert_eq!(session.completed(), (0, 2));
+        assert!(session.current_is_new());
+        session.next_card(true, &deck_config);
+        session.next_card(true, &deck_config);
+        assert_eq!(session.completed(), (2, 2));
+    }
+
+    #[test]
+    fn warm_up() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for (deck, deck_reverse) in [(1, 1), (4, 2), (3, 4)] {
+            dataset.cards.push(test_card(
+                "hello",
+                "hola",
+                Some(VocabMetadata {
+                    deck,
+                    deck_reverse,
+                    ..Default::default()
+                }),
+            ));
+        }
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        session.warm_up(2);
+        let order = session
+            .queue
+            .iter()
+            .map(|item| (item.card, item.reverse))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (1, false),
+                (2, true),
+                (0, false),
+                (2, false),
+                (0, true),
+                (1, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_current_dataset() {
+        let dir = std::env::temp_dir().join(format!("ruvola-split-{}", std::process::id()));
+        let file_path = dir.join("learned.tsv").to_string_lossy().to_string();
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for (word, deck) in [("one", 1), ("two", 5), ("three", 2), ("four", 6)] {
+            dataset.cards.push(test_card(
+                word,
+                word,
+                Some(VocabMetadata {
+                    deck,
+                    deck_reverse: deck,
+                    ..Default::default()
+                }),
+            ));
+        }
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(session.split_current_dataset(5, &file_path).unwrap(), 2);
+        assert_eq!(session.datasets.len(), 2);
+        assert_eq!(session.datasets[1].file_path, file_path);
+        let words = |dataset: &VocaCardDataset| {
+            dataset
+                .cards
+                .iter()
+                .map(|card| card.word_a.base.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words(&session.datasets[0]), vec!["one", "three"]);
+        assert_eq!(words(&session.datasets[1]), vec!["two", "four"]);
+
+        // The queue still points at the same cards
+        let queued = session
+            .queue
+            .iter()
+            .take(4)
+            .map(|item| {
+                session.datasets[item.dataset].cards[item.card]
+                    .word_a
+                    .base
+                    .as_str()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(queued, vec!["one", "two", "three", "four"]);
+        assert_eq!(session.change_summary().files.len(), 2);
+
+        assert!(session.split_current_dataset(5, "test.txt").is_err());
+    }
+
+    #[test]
+    fn existing_local_copy() {
+        let dir = std::env::temp_dir().join(format!("ruvola-save-to-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let local_copy = dir.join("week1.tsv").to_string_lossy().to_string();
+        let mut dataset = VocaCardDataset::new(&local_copy, "English", "Spanish");
+        dataset.cards.push(test_card("one", "uno", None));
+        dataset.save().unwrap();
+
+        // The deck is not downloaded again, so its progress is kept
+        let options = SessionOptions {
+            save_to: Some(dir.clone()),
+            ..Default::default()
+        };
+        let session = VocaSession::from_files(
+            &["https://example.com/class/week1.tsv".to_string()],
+            &options,
+            &AppConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(session.datasets[0].file_path, local_copy);
+        assert!(!session.datasets[0].read_only);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resume_memorization_round() {
+        let dir = std::env::temp_dir().join(format!("ruvola-resume-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("deck.tsv").to_string_lossy().to_string();
+        let mut dataset = VocaCardDataset::new(&file_path, "English", "Spanish");
+        for word in ["one", "two", "three"] {
+            dataset.cards.push(test_card(word, word, None));
+        }
+        let memorization = MemorizationConfig::default();
+        let deck_config = DeckConfig::default();
+        let mut session =
+            VocaSession::new(vec![dataset], &SessionOptions::default(), &memorization);
+        // Quit after memorizing the first card
+        session.next_card(true, &deck_config);
+        session.save().unwrap();
+
+        let dataset = VocaCardDataset::from_file(&file_path).unwrap();
+        let session = VocaSession::new(vec![dataset], &SessionOptions::default(), &memorization);
+        let items = session
+            .queue
+            .iter()
+            .map(|item| (item.card, item.reverse, item.memorization_card))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items[..3],
+            [(1, false, true), (2, false, true), (0, false, false)]
+        );
+        assert_eq!(items.len(), 8);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn spread_related_cards() {
+        let spread = spread_groups(
+            vec![
+                (0, 'a'),
+                (0, 'b'),
+                (0, 'c'),
+                (0, 'd'),
+                (1, 'x'),
+                (1, 'y'),
+                (2, 'z'),
+            ],
+            |(group, _)| *group,
+        );
+        let order = spread.iter().map(|(_, item)| *item).collect::<String>();
+        assert_eq!(order, "axbzcyd");
+
+        let mut datasets = Vec::new();
+        for (name, words) in [
+            ("a.tsv", ["one", "two", "three"]),
+            ("b.tsv", ["uno", "dos", "tres"]),
+        ] {
+            let mut dataset = VocaCardDataset::new(name, "en", "es");
+            for word in words {
+                dataset.cards.push(test_card(word, word, None));
+            }
+            datasets.push(dataset);
+        }
+        let options = SessionOptions {
+            spread_related: true,
+            ..Default::default()
+        };
+        let memorization = MemorizationConfig {
+            do_memorization_round: false,
+            ..Default::default()
+        };
+        let session = VocaSession::new(datasets, &options, &memorization);
+        let forward = session
+            .queue
+            .iter()
+            .filter(|item| !item.reverse)
+            .map(|item| (item.dataset, item.card))
+            .collect::<Vec<_>>();
+        assert_eq!(forward, [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn read_only_datasets() {
+        assert_eq!(split_read_only("deck.tsv:ro"), ("deck.tsv", true));
+        assert_eq!(split_read_only("deck.tsv"), ("deck.tsv", false));
+
+        let mut datasets = Vec::new();
+        for (name, read_only) in [("mine.tsv", false), ("shared.tsv", true)] {
+            let mut dataset = VocaCardDataset::new(name, "English", "Spanish");
+            dataset.read_only = read_only;
+            dataset.overrides.forward_only = true;
+            dataset
+                .cards
+                .push(test_card("hello", "hola", Some(VocabMetadata::default())));
+            datasets.push(dataset);
+        }
+        let options = SessionOptions {
+            sort_mode: SortMode::Original,
+            ..Default::default()
+        };
+        let mut session = VocaSession::new(datasets, &options, &MemorizationConfig::default());
+        let deck_config = DeckConfig::default();
+
+        // The shared card is scheduled for the session, but nothing is left to save
+        session.queue.rotate_left(1);
+        assert!(session.split_current_dataset(0, "split.tsv").is_err());
+        assert!(session.next_card(true, &deck_config).is_some());
+        let metadata = session.datasets[1].cards[0].metadata.as_ref().unwrap();
+        assert!(metadata.last_reviewed.is_some());
+        assert!(!session.has_changes());
+
+        session.next_card(true, &deck_config);
+        assert_eq!(session.change_summary().files, vec!["mine.tsv"]);
+    }
+
+    #[test]
+    fn add_card() {
+        let dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        assert!(session.current_task().is_none());
+        let synonyms = Synonyms(HashMap::from([(
+            "cat".to_string(),
+            vec!["gato".to_string(), "michi".to_string()],
+        )]));
+
+        session
+            .add_card(0, "cat", "@cat", false, &synonyms)
+            .unwrap();
+        assert!(session.has_changes());
+        assert!(session.current_task().is_none());
+        assert_eq!(
+            session.datasets[0].cards[0].word_b.variants,
+            ["gato", "michi"]
+        );
+
+        session
+            .add_card(0, "dog", "perro", true, &synonyms)
+            .unwrap();
+        assert_eq!(session.datasets[0].cards.len(), 2);
+        assert_eq!(session.total_tasks(), 2);
+        let task = session.current_task().unwrap();
+        assert_eq!((task.query, task.answer), ("dog", "perro"));
+        assert!(session.current_is_new());
+        session.next_card(true, &DeckConfig::default());
+        assert_eq!(session.current_task().unwrap().query, "perro");
+
+        session.datasets[0].read_only = true;
+        assert!(
+            session
+                .add_card(0, "bird", "pájaro", false, &synonyms)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn preview_beyond_last_deck() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        dataset.cards.push(test_card(
+            "dog",
+            "perro",
+            // Stored before the deck intervals were shortened
+            Some(VocabMetadata {
+                deck: 20,
+                deck_reverse: 20,
+                ..Default::default()
+            }),
+        ));
+        let options = SessionOptions {
+            sort_mode: SortMode::Original,
+            ..Default::default()
+        };
+        let mut session = VocaSession::new(vec![dataset], &options, &MemorizationConfig::default());
+        let deck_config = DeckConfig::default();
+        let intervals = &deck_config.deck_intervals;
+        let last_deck = intervals.len() - 1;
+        assert_eq!(
+            session.preview_interval(Grade::Hard, &deck_config),
+            Some(intervals[last_deck].0)
+        );
+        // A wrong answer drops the card one deck below the last one
+        assert_eq!(
+            session.preview_interval(Grade::Wrong, &deck_config),
+            Some(intervals[last_deck - 1].0)
+        );
+
+        session.next_card(true, &deck_config);
+        session.next_card(false, &deck_config);
+        let card = &session.datasets[0].cards[0];
+        assert_eq!(card.get_deck(false), Some(last_deck as u8));
+        assert_eq!(card.get_deck(true), Some(last_deck as u8 - 1));
+    }
+
+    #[test]
+    fn review_cap() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for word in ["one", "two", "three"] {
+            dataset
+                .cards
+                .push(test_card(word, word, Some(VocabMetadata::default())));
+        }
+        let options = SessionOptions {
+            sort_mode: SortMode::Original,
+            review_cap: Some(3),
+            ..Default::default()
+        };
+        let session = VocaSession::new(vec![dataset], &options, &MemorizationConfig::default());
+        let queued = session
+            .queue
+            .iter()
+            .map(|item| (item.card, item.reverse))
+            .collect::<Vec<_>>();
+        assert_eq!(queued, [(0, false), (1, false), (0, true)]);
+    }
+
+    #[test]
+    fn new_limit() {
+        let datasets = ["a.txt", "b.txt"].map(|file_path| {
+            let mut dataset = VocaCardDataset::new(file_path, "English", "Spanish");
+            for word in ["one", "two"] {
+                dataset.cards.push(test_card(word, word, None));
+            }
+            dataset
+        });
+        let options = SessionOptions {
+            sort_mode: SortMode::Original,
+            new_limit: Some(3),
+            ..Default::default()
+        };
+        let memorization_config = MemorizationConfig {
+            do_memorization_round: true,
+            ..Default::default()
+        };
+        let mut session = VocaSession::new(datasets.into(), &options, &memorization_config);
+        let memorized = session
+            .queue
+            .iter()
+            .filter(|item| item.memorization_card)
+            .map(|item| (item.dataset, item.card))
+            .collect::<Vec<_>>();
+        assert_eq!(memorized, [(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(session.total_tasks(), 9);
+
+        session.next_card(true, &DeckConfig::default());
+        assert_eq!(session.introduced(), 1);
+    }
+
+    #[test]
+    fn grep() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for (word_a, word_b, hint) in [
+            ("to eat", "comer", None),
+            ("dog", "perro", Some("Verb-free zone")),
+            ("cat", "gato", None),
+            ("to run", "correr", Some("irregular verb")),
+        ] {
+            dataset.cards.push(Vocab {
+                hint: hint.map(str::to_string),
+                ..test_card(word_a, word_b, Some(VocabMetadata::default()))
+            });
+        }
+        let options = SessionOptions {
+            sort_mode: SortMode::Original,
+            grep: Some("verb".to_string()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        let session = VocaSession::new(vec![dataset], &options, &MemorizationConfig::default());
+        assert_eq!(session.total_tasks(), 2);
+        assert!(session.queue.iter().all(|item| item.card == 1));
+        assert!(session.any_card_matches("co"));
+        assert!(!session.any_card_matches("bird"));
+    }
+
+    #[test]
+    fn work_ahead() {
+        let now = chrono::Local::now().naive_utc();
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for (word, due_in_hours) in [("today", -1), ("tomorrow", 20), ("next week", 24 * 7)] {
+            dataset.cards.push(test_card(
+                word,
+                word,
+                Some(VocabMetadata {
+                    deck: 2,
+                    due_date: now + chrono::Duration::hours(due_in_hours),
+                    deck_reverse: 2,
+                    due_date_reverse: now + chrono::Duration::hours(due_in_hours + 1),
+                    ..Default::default()
+                }),
+            ));
+        }
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions {
+                work_ahead: Some(chrono::Duration::days(1)),
+                ..Default::default()
+            },
+            &MemorizationConfig::default(),
+        );
+        // Nothing is loaded while cards are left
+        assert_eq!(session.start_work_ahead(), 0);
+        session.next_card(true, &deck_config);
+        session.next_card(true, &deck_config);
+        assert!(session.current_task().is_none());
+
+        // The card answered today is scheduled a week ahead and not loaded again
+        assert_eq!(session.work_ahead_count(), Some(2));
+        assert_eq!(session.start_work_ahead(), 2);
+        assert_eq!(session.total_tasks(), 4);
+        assert_eq!(session.current_task().unwrap().query, "tomorrow");
+        session.next_card(true, &deck_config);
+        session.next_card(true, &deck_config);
+        assert_eq!(session.work_ahead_count(), Some(0));
+        assert_eq!(session.datasets[0].cards[1].get_deck(false), Some(3));
+    }
+
+    #[test]
+    fn work_ahead_top_up() {
+        let now = chrono::Local::now().naive_utc();
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        dataset.overrides.forward_only = true;
+        for (word, due_in_hours) in [("a", -2), ("b", -1), ("c", 2), ("d", 3), ("e", 4)] {
+            dataset.cards.push(test_card(
+                word,
+                word,
+                Some(VocabMetadata {
+                    deck: 2,
+                    due_date: now + chrono::Duration::hours(due_in_hours),
+                    ..Default::default()
+                }),
+            ));
+        }
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions {
+                work_ahead: Some(chrono::Duration::days(1)),
+                ..Default::default()
+            },
+            &MemorizationConfig::default(),
+        );
+        session.work_ahead_low_watermark = 2;
+        session.work_ahead_batch_size = 2;
+        let queries = |session: &VocaSession| {
+            session
+                .queue
+                .iter()
+                .map(|item| session.datasets[0].cards[item.card].word_a.base.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(queries(&session), ["a", "b"]);
+
+        // One task is left, so the next two cards are added
+        session.next_card(true, &deck_config);
+        assert_eq!(queries(&session), ["b", "c", "d"]);
+        assert_eq!(session.total_tasks(), 4);
+        session.next_card(false, &deck_config);
+        assert_eq!(queries(&session), ["c", "d", "b"]);
+        session.next_card(true, &deck_config);
+        session.next_card(true, &deck_config);
+        // "b" was answered wrong and is still queued, so only "e" is left to add
+        assert_eq!(queries(&session), ["b", "e"]);
+    }
+
+    #[test]
+    fn hard_grade() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        dataset.cards.push(test_card(
+            "hello",
+            "hola",
+            Some(VocabMetadata {
+                deck: 2,
+                deck_reverse: 2,
+                ..Default::default()
+            }),
+        ));
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        assert_eq!(
+            session.preview_interval(Grade::Hard, &deck_config),
+            Some(deck_config.interval(2))
+        );
+        let outcome = session.grade_card(Grade::Hard, &deck_config).unwrap();
+        assert_eq!((outcome.old_deck, outcome.new_deck), (Some(2), 2));
+        // Hard answers are no lapses and the card is not asked again
+        assert_eq!(session.datasets[0].cards[0].lapses, 0);
+        assert_eq!(session.remaining(), 1);
+    }
+
+    #[test]
+    fn focus_dataset() {
+        let datasets = ["a.tsv", "b.tsv"].map(|file_path| {
+            let mut dataset = VocaCardDataset::new(file_path, "English", "Spanish");
+            dataset
+                .cards
+                .push(test_card("hello", "hola", Some(VocabMetadata::default())));
+            dataset
+        });
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            datasets.into(),
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        session.next_card(true, &deck_config);
+        session.set_focus(Some(1));
+        assert!(session.queue.iter().all(|item| item.dataset == 1));
+        assert_eq!(session.current_progress(), 1);
+        assert_eq!(session.total_tasks(), 4);
+
+        session.next_card(true, &deck_config);
+        session.next_card(true, &deck_config);
+        // The focused dataset is done, the session continues with the others
+        assert_eq!(session.focus(), None);
+        assert_eq!(session.queue.len(), 1);
+        assert_eq!(session.queue[0].dataset, 0);
+        assert_eq!(session.current_progress(), 3);
+    }
+
+    #[test]
+    fn study_batch() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for i in 0..5 {
+            dataset.cards.push(test_card(
+                &format!("word {}", i),
+                &format!("palabra {}", i),
+                Some(VocabMetadata::default()),
+            ));
+        }
+        let deck_config = DeckConfig::default();
+        let mut session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+        session.set_batch_size(Some(3));
+        assert_eq!(session.queue.len(), 3);
+        assert_eq!(session.total_tasks(), 10);
+
+        session.next_card(false, &deck_config);
+        assert_eq!(session.queue.len(), 3);
+        // The wrong card is repeated within the batch, before the reserved cards
+        assert_eq!(session.queue[2].card, 0);
+        assert_eq!(session.reserve.front().unwrap().card, 3);
+
+        let mut answered = 1;
+        while session.current_task().is_some() {
+            session.next_card(true, &deck_config);
+            answered += 1;
+            assert!(session.queue.len() <= 3);
+        }
+        assert_eq!(answered, 11);
+        assert_eq!(session.current_progress(), session.total_tasks());
+    }
+
+    #[test]
+    fn deck_range() {
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for (deck, deck_reverse) in [(0, 5), (2, 3)] {
+            dataset.cards.push(test_card(
+                "hello",
+                "hola",
+                Some(VocabMetadata {
+                    deck,
+                    deck_reverse,
+                    ..Default::default()
+                }),
+            ));
+        }
+        let session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions {
+                deck_range: Some(parse_deck_range("1..=3").unwrap()),
+                ..Default::default()
+            },
+            &MemorizationConfig::default(),
+        );
+        let items = session
+            .queue
+            .iter()
+            .map(|item| (item.card, item.reverse))
+            .collect::<Vec<_>>();
+        assert_eq!(items, vec![(1, false), (1, true)]);
+    }
+
+    #[test]
+    fn health_metrics() {
+        let now = NaiveDateTime::parse_from_str("2025-01-10 12:00:00", DATE_FORMAT).unwrap();
+        let mut dataset = VocaCardDataset::new("test.txt", "English", "Spanish");
+        for (deck, due_in_days, lapses) in [(1, -3, 0), (3, 2, 2)] {
+            dataset.cards.push(Vocab {
+                lapses,
+                ..test_card(
+                    "hello",
+                    "hola",
+                    Some(VocabMetadata {
+                        deck,
+                        due_date: now + chrono::Duration::days(due_in_days),
+                        deck_reverse: deck,
+                        due_date_reverse: now + chrono::Duration::days(due_in_days),
+                        ..Default::default()
+                    }),
+                )
+            });
+        }
+        dataset.cards.push(test_card("unseen", "nuevo", None));
+        let session = VocaSession::new(
+            vec![dataset],
+            &SessionOptions::default(),
+            &MemorizationConfig::default(),
+        );
+
+        let health = session.deck_health();
+        assert_eq!(health[0].seen_cards, 2);
+        assert_eq!(health[0].average_deck, 2.0);
+        assert_eq!(health[0].never_lapsed, 0.5);
+        assert_eq!(session.projected_reviews(now, 3), vec![2, 0, 2]);
     }
 }
@@ -0,0 +1,182 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+
+use crate::config::{SpeechConfig, get_system_config_dir};
+
+/// Runs [`synthesize`] on a separate thread, so that a slow speech command does not block the
+/// terminal UI. The file is sent once it is ready.
+pub fn synthesize_in_background(
+    config: &SpeechConfig,
+    text: &str,
+    language: &str,
+) -> Receiver<Result<PathBuf>> {
+    let (sender, receiver) = mpsc::channel();
+    let (config, text, language) = (config.clone(), text.to_string(), language.to_string());
+    std::thread::spawn(move || {
+        // Nobody waits for the file anymore if the card was answered in the meantime
+        let _ = sender.send(synthesize(&config, &text, &language));
+    });
+    receiver
+}
+
+/// Returns a WAV file of `text` spoken in `language`, synthesized with the speech command.
+///
+/// With caching enabled, the file is kept in `ruvola/speech_cache` next to the config file and
+/// reused whenever the same text is spoken in the same language again.
+pub fn synthesize(config: &SpeechConfig, text: &str, language: &str) -> Result<PathBuf> {
+    let cache_dir = config
+        .cache
+        .then(|| get_system_config_dir().ok())
+        .flatten()
+        .map(|config_dir| PathBuf::from(config_dir).join("ruvola/speech_cache"));
+    let key = cache_key(text, language);
+    let output = match &cache_dir {
+        Some(cache_dir) => {
+            let output = cache_dir.join(format!("{:016x}.wav", key));
+            if output.exists() {
+                // The modification time orders the files for the eviction
+                std::fs::File::options()
+                    .append(true)
+                    .open(&output)?
+                    .set_modified(SystemTime::now())?;
+                return Ok(output);
+            }
+            std::fs::create_dir_all(cache_dir)?;
+            output
+        }
+        None => std::env::temp_dir().join(format!(
+            "ruvola-speech-{}-{:016x}.wav",
+            std::process::id(),
+            key
+        )),
+    };
+    run_command(&config.command, text, language, &output)?;
+    if let Some(cache_dir) = &cache_dir
+        && config.cache_max_mb > 0
+    {
+        evict(cache_dir, config.cache_max_mb * 1024 * 1024, &output)
+            .context("Could not clean up the speech cache")?;
+    }
+    Ok(output)
+}
+
+/// Runs the speech command with `%t` replaced by the text, `%l` by the language and `%o` by
+/// the output file.
+fn run_command(command: &[String], text: &str, language: &str, output: &Path) -> Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        anyhow::bail!("No speech command is configured");
+    };
+    let output_str = output.to_string_lossy();
+    let substitute = |arg: &String| {
+        arg.replace("%t", text)
+            .replace("%l", language)
+            .replace("%o", &output_str)
+    };
+    // Any output of the command would garble the terminal UI
+    let status = Command::new(substitute(program))
+        .args(args.iter().map(substitute))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run speech command '{}'", program))?;
+    if !status.success() {
+        anyhow::bail!("Speech command exited with {}", status);
+    }
+    if !output.exists() {
+        anyhow::bail!("Speech command did not write {}", output.display());
+    }
+    Ok(())
+}
+
+/// FNV-1a hash of the language and the text, which stays the same across builds unlike the
+/// hasher of the standard library.
+fn cache_key(text: &str, language: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    [language.as_bytes(), &[0], text.as_bytes()]
+        .concat()
+        .iter()
+        .fold(OFFSET_BASIS, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(PRIME)
+        })
+}
+
+/// Deletes the least recently used files of `dir` until they take up at most `max_bytes`.
+/// `keep` is never deleted, even if it is larger than `max_bytes` on its own.
+fn evict(dir: &Path, max_bytes: u64, keep: &Path) -> std::io::Result<()> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+    let mut total = files.iter().map(|(_, len, _)| len).sum::<u64>();
+    files.sort_unstable();
+    for (_, len, path) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+        std::fs::remove_file(path)?;
+        total -= len;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn cache_key_depends_on_language() {
+        assert_eq!(cache_key("hola", "es"), cache_key("hola", "es"));
+        assert_ne!(cache_key("hola", "es"), cache_key("hola", "pt"));
+        assert_ne!(cache_key("ab", "c"), cache_key("b", "ca"));
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let dir = std::env::temp_dir().join(format!("ruvola-speech-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [("old.wav", 30), ("new.wav", 10), ("mid.wav", 20)] {
+            let path = dir.join(name);
+            std::fs::write(&path, [0; 100]).unwrap();
+            std::fs::File::options()
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+
+        evict(&dir, 200, &dir.join("new.wav")).unwrap();
+        let mut left = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        left.sort();
+        assert_eq!(left, ["mid.wav", "new.wav"]);
+
+        // The file that was just synthesized is kept even if it is too large for the cache
+        evict(&dir, 0, &dir.join("new.wav")).unwrap();
+        let left = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(left, ["new.wav"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
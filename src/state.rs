@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_system_config_dir;
+
+const STATE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Statistics that are kept across sessions in `ruvola/state.toml` next to the config file.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct AppState {
+    /// Number of reviewed cards per day, keyed by local date
+    reviews: BTreeMap<String, usize>,
+    /// Number of cards that were seen for the first time per day, keyed by local date
+    new_cards: BTreeMap<String, usize>,
+    /// Reviews of the running session, which only count once its changes are saved
+    #[serde(skip)]
+    unsaved_reviews: BTreeMap<String, usize>,
+    /// New cards of the running session, which only count once its changes are saved
+    #[serde(skip)]
+    unsaved_new_cards: BTreeMap<String, usize>,
+}
+
+impl AppState {
+    fn file_path() -> Option<String> {
+        get_system_config_dir()
+            .ok()
+            .map(|config_dir| format!("{}/ruvola/state.toml", config_dir))
+    }
+
+    /// Loads the state file, or returns an empty state if there is none.
+    pub fn load() -> Result<Self> {
+        match Self::file_path() {
+            Some(file_path) if std::fs::exists(&file_path)? => {
+                Ok(toml::de::from_str(&std::fs::read_to_string(file_path)?)?)
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(file_path) = Self::file_path() else {
+            log::warn!("Cannot locate the config directory, statistics are not saved");
+            return Ok(());
+        };
+        if let Some(parent) = std::path::Path::new(&file_path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(file_path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn record_review(&mut self, date: NaiveDate) {
+        *self
+            .unsaved_reviews
+            .entry(date.format(STATE_DATE_FORMAT).to_string())
+            .or_default() += 1;
+    }
+
+    pub fn record_new_card(&mut self, date: NaiveDate) {
+        *self
+            .unsaved_new_cards
+            .entry(date.format(STATE_DATE_FORMAT).to_string())
+            .or_default() += 1;
+    }
+
+    /// Keeps the counts recorded since the last commit. Called once the reviews they belong to
+    /// were saved, so that discarded sessions do not count towards the goals and limits.
+    pub fn commit(&mut self) {
+        for (committed, unsaved) in [
+            (&mut self.reviews, &mut self.unsaved_reviews),
+            (&mut self.new_cards, &mut self.unsaved_new_cards),
+        ] {
+            for (date, count) in std::mem::take(unsaved) {
+                *committed.entry(date).or_default() += count;
+            }
+        }
+    }
+
+    /// New cards of `date`, including the ones of the running session.
+    pub fn new_cards_on(&self, date: NaiveDate) -> usize {
+        count_on(&self.new_cards, date) + count_on(&self.unsaved_new_cards, date)
+    }
+
+    /// Reviews of `date`, including the ones of the running session.
+    pub fn reviews_on(&self, date: NaiveDate) -> usize {
+        count_on(&self.reviews, date) + count_on(&self.unsaved_reviews, date)
+    }
+
+    /// Number of consecutive days up to `today` on which the daily goal was reached. Today
+    /// only breaks the streak once it is over.
+    pub fn streak(&self, today: NaiveDate, daily_goal: usize) -> usize {
+        let reached = |date| self.reviews_on(date) >= daily_goal.max(1);
+        let mut streak = 0;
+        let mut date = today;
+        if reached(today) {
+            streak += 1;
+        }
+        while let Some(previous) = date.checked_sub_days(Days::new(1))
+            && reached(previous)
+        {
+            streak += 1;
+            date = previous;
+        }
+        streak
+    }
+}
+
+fn count_on(counts: &BTreeMap<String, usize>, date: NaiveDate) -> usize {
+    counts
+        .get(&date.format(STATE_DATE_FORMAT).to_string())
+        .copied()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streak() {
+        let day = |d| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+        let mut state = AppState::default();
+        for (date, reviews) in [(1, 10), (3, 10), (4, 3), (5, 12)] {
+            for _ in 0..reviews {
+                state.record_review(day(date));
+            }
+        }
+        assert_eq!(state.reviews_on(day(5)), 12);
+        state.record_new_card(day(5));
+        assert_eq!(state.new_cards_on(day(5)), 1);
+        assert_eq!(state.new_cards_on(day(4)), 0);
+        assert_eq!(state.streak(day(5), 10), 1);
+        assert_eq!(state.streak(day(5), 3), 3);
+        // The goal for today has not been reached yet
+        assert_eq!(state.streak(day(6), 3), 3);
+        // Missing a day resets the streak
+        assert_eq!(state.streak(day(7), 3), 0);
+
+        state.commit();
+        let parsed: AppState = toml::de::from_str(&toml::to_string(&state).unwrap()).unwrap();
+        assert_eq!(parsed, state);
+    }
+
+    #[test]
+    fn discarded_session() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let mut state = AppState::default();
+        for _ in 0..3 {
+            state.record_review(today);
+        }
+        state.commit();
+        let saved = toml::to_string(&state).unwrap();
+
+        // A session that is quit without saving
+        for _ in 0..5 {
+            state.record_review(today);
+        }
+        state.record_new_card(today);
+        assert_eq!(state.reviews_on(today), 8);
+        assert_eq!(toml::to_string(&state).unwrap(), saved);
+        // The next session still has the rest of the daily cap and new card limit
+        let next: AppState = toml::de::from_str(&saved).unwrap();
+        assert_eq!(next.reviews_on(today), 3);
+        assert_eq!(next.new_cards_on(today), 0);
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+/// Measures the time spent actively in a session. Gaps between key presses that are longer
+/// than the idle timeout only count up to the timeout.
+pub struct SessionTimer {
+    /// Active time up to `last_activity`
+    active: Duration,
+    last_activity: Instant,
+    idle_timeout: Option<Duration>,
+}
+
+impl SessionTimer {
+    pub fn new(now: Instant, idle_timeout: Option<Duration>) -> Self {
+        SessionTimer {
+            active: Duration::ZERO,
+            last_activity: now,
+            idle_timeout,
+        }
+    }
+
+    pub fn record_activity(&mut self, now: Instant) {
+        self.active = self.elapsed(now);
+        self.last_activity = now;
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        let since_activity = now.saturating_duration_since(self.last_activity);
+        match self.idle_timeout {
+            Some(idle_timeout) => self.active + since_activity.min(idle_timeout),
+            None => self.active + since_activity,
+        }
+    }
+
+    /// Whether the timer stopped because there was no activity for the idle timeout.
+    pub fn is_paused(&self, now: Instant) -> bool {
+        self.idle_timeout.is_some_and(|idle_timeout| {
+            now.saturating_duration_since(self.last_activity) >= idle_timeout
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_time_is_not_counted() {
+        let start = Instant::now();
+        let secs = |n| start + Duration::from_secs(n);
+        let mut timer = SessionTimer::new(start, Some(Duration::from_secs(60)));
+        timer.record_activity(secs(30));
+        assert_eq!(timer.elapsed(secs(50)), Duration::from_secs(50));
+        assert!(!timer.is_paused(secs(50)));
+
+        // Away for ten minutes
+        assert!(timer.is_paused(secs(630)));
+        assert_eq!(timer.elapsed(secs(630)), Duration::from_secs(90));
+        timer.record_activity(secs(630));
+        assert!(!timer.is_paused(secs(640)));
+        assert_eq!(timer.elapsed(secs(640)), Duration::from_secs(100));
+
+        let timer = SessionTimer::new(start, None);
+        assert!(!timer.is_paused(secs(600)));
+        assert_eq!(timer.elapsed(secs(600)), Duration::from_secs(600));
+    }
+}